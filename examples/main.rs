@@ -1,9 +1,9 @@
 use spatialos_codegen::ASTBuilder;
 
-fn main() {
+fn main() -> Result<(), spatialos_codegen::Error> {
     let schema = ASTBuilder::default()
         .with_directory("./examples/schema_old")
         .with_directory("./examples/schema")
-        .build();
-    let _result = schema.generate("./examples/test/src/generated", "generated");
+        .build()?;
+    schema.generate("./examples/test/src/generated", "generated")
 }
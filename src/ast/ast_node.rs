@@ -1,7 +1,9 @@
 use crate::ast::PackageNode;
 use crate::ast::SchemaFile;
-use std::fs::File;
-use std::io::Write;
+use crate::codegen::{module_file_name, rust_ident, rust_name};
+use crate::error::Error;
+use crate::output::{Formatting, OutputFile, HEADER};
+use quote::__private::TokenStream;
 use std::path::Path;
 
 #[derive(Debug, Eq, PartialEq)]
@@ -11,6 +13,14 @@ pub enum ASTNode {
 }
 
 impl ASTNode {
+    /// Collects the schema files of this node and of the packages under it.
+    pub(crate) fn schemas<'a>(&'a self, schemas: &mut Vec<&'a SchemaFile>) {
+        match self {
+            Self::PackageNode(package) => package.inner.iter().for_each(|n| n.schemas(schemas)),
+            Self::SchemaNode(schema) => schemas.push(schema),
+        }
+    }
+
     fn get_export(&self) -> (String, Vec<String>) {
         match self {
             Self::PackageNode(pn) => (pn.name.clone(), Vec::new()),
@@ -22,31 +32,82 @@ impl ASTNode {
         data.iter().map(Self::get_export).collect()
     }
 
-    pub fn generate_mod_rs<P: AsRef<Path> + Clone>(
-        nodes: &[Self],
-        path: P,
-    ) -> Result<(), std::io::Error> {
-        std::fs::create_dir_all(path.clone()).map(|_| {
-            let mut file = File::create(path.clone().as_ref().join("mod.rs"))?;
-            for module in Self::get_exports(nodes) {
-                writeln!(file, "pub mod {};", module.0)?;
-                for usage in module.1 {
-                    writeln!(file, "pub use {}::{};", module.0, usage)?;
-                }
+    /// The `mod.rs` in the directory `path` declaring the modules of `nodes`.
+    pub fn generate_mod_rs<P: AsRef<Path>>(nodes: &[Self], path: P) -> OutputFile {
+        let mut contents = HEADER.to_owned();
+        for (module, usages) in Self::get_exports(nodes) {
+            let module = rust_name(&module);
+            contents += &format!("pub mod {};\n", module);
+            for usage in usages {
+                contents += &format!("pub use {}::{};\n", module, rust_name(&usage));
             }
-            Ok(())
-        })?
+        }
+        (path.as_ref().join("mod.rs"), contents)
     }
 
-    pub fn generate_node<P: AsRef<Path> + Clone>(&self, path: P) -> Result<(), std::io::Error> {
+    /// The modules of `nodes` as inline `mod` items, along with the same
+    /// re-exports `generate_mod_rs` writes.
+    pub fn generate_modules(nodes: &[Self], module: &str) -> Result<TokenStream, Error> {
+        let modules = nodes
+            .iter()
+            .map(|node| node.generate_module(module))
+            .collect::<Result<Vec<_>, _>>()?;
+        let exports = Self::get_exports(nodes)
+            .into_iter()
+            .flat_map(|(module, usages)| {
+                let module = rust_ident(&module);
+                usages.into_iter().map(move |usage| {
+                    let usage = rust_ident(&usage);
+                    quote! { pub use #module::#usage; }
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(quote! {
+            #(#modules)*
+            #(#exports)*
+        })
+    }
+
+    fn generate_module(&self, module: &str) -> Result<TokenStream, Error> {
+        let (name, inner) = match self {
+            Self::SchemaNode(schema) => (
+                &schema.name,
+                schema
+                    .generate(module)
+                    .map_err(|e| e.in_file(&schema.path))?,
+            ),
+            Self::PackageNode(package) => (
+                &package.name,
+                Self::generate_modules(&package.inner, module)?,
+            ),
+        };
+        let name = rust_ident(name);
+        Ok(quote! {
+            pub mod #name {
+                #inner
+            }
+        })
+    }
+
+    /// The files of this node when generated in the directory `path`.
+    pub fn generate_node<P: AsRef<Path>>(
+        &self,
+        path: P,
+        module: &str,
+        formatting: Formatting,
+    ) -> Result<Vec<OutputFile>, Error> {
         match self {
-            Self::SchemaNode(node) => node.generate_schema(path),
+            Self::SchemaNode(node) => Ok(vec![node.generate_schema(path, module, formatting)?]),
             Self::PackageNode(node) => {
-                let name = node.name.clone();
-                for node in &node.inner {
-                    Self::generate_node(node, path.as_ref().join(&name))?;
-                }
-                Self::generate_mod_rs(&node.inner, path.as_ref().join(&name))
+                let path = path.as_ref().join(module_file_name(&node.name));
+                let mut files = node
+                    .inner
+                    .iter()
+                    .map(|node| node.generate_node(&path, module, formatting))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .concat();
+                files.push(Self::generate_mod_rs(&node.inner, &path));
+                Ok(files)
             }
         }
     }
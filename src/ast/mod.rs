@@ -2,33 +2,52 @@ pub mod ast_node;
 pub mod root;
 pub mod schema_file;
 
-use std::convert::identity;
-
 pub use ast_node::ASTNode;
 pub use root::{ASTBuilder, AST};
 pub use schema_file::SchemaFile;
 
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Annotation {
+    pub name: Vec<String>,
+    pub values: Vec<AnnotationValue>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum AnnotationValue {
+    Bool(bool),
+    Number(String),
+    String(String),
+    List(Vec<AnnotationValue>),
+    Map(Vec<(AnnotationValue, AnnotationValue)>),
+    Reference(Vec<String>),
+    Instance(Annotation),
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Command {
     pub name: String,
     pub r_type: DataType,
     pub args: Vec<DataType>,
+    pub comments: Vec<String>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Event {
     pub name: String,
     pub r_type: DataType,
+    pub comments: Vec<String>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Component {
     pub name: String,
     pub id: u32,
+    pub data: Option<DataType>,
     pub members: Vec<Member>,
     pub events: Vec<Event>,
     pub commands: Vec<Command>,
     pub comments: Vec<String>,
+    pub annotations: Vec<Annotation>,
     pub enums: Vec<Enum>,
     pub types: Vec<Type>,
 }
@@ -39,10 +58,7 @@ impl Component {
     }
 
     pub fn get_exports(data: &[Self]) -> Vec<String> {
-        data.iter()
-            .map(Self::get_export)
-            .filter_map(identity)
-            .collect()
+        data.iter().filter_map(Self::get_export).collect()
     }
 }
 
@@ -52,6 +68,7 @@ pub struct Member {
     pub m_type: DataType,
     pub id: u32,
     pub comments: Vec<String>,
+    pub annotations: Vec<Annotation>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -59,12 +76,14 @@ pub struct Variant {
     pub name: String,
     pub id: u32,
     pub comments: Vec<String>,
+    pub annotations: Vec<Annotation>,
 }
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Type {
     pub name: String,
     pub members: Vec<Member>,
     pub comments: Vec<String>,
+    pub annotations: Vec<Annotation>,
     pub types: Vec<Type>,
     pub enums: Vec<Enum>,
 }
@@ -75,10 +94,7 @@ impl Type {
     }
 
     pub fn get_exports(data: &[Self]) -> Vec<String> {
-        data.iter()
-            .map(Self::get_export)
-            .filter_map(identity)
-            .collect()
+        data.iter().filter_map(Self::get_export).collect()
     }
 }
 #[derive(Debug, Eq, PartialEq)]
@@ -117,6 +133,7 @@ pub struct Enum {
     pub name: String,
     pub variants: Vec<Variant>,
     pub comments: Vec<String>,
+    pub annotations: Vec<Annotation>,
 }
 
 impl Enum {
@@ -125,10 +142,7 @@ impl Enum {
     }
 
     pub fn get_exports(data: &[Self]) -> Vec<String> {
-        data.iter()
-            .map(Self::get_export)
-            .filter_map(identity)
-            .collect()
+        data.iter().filter_map(Self::get_export).collect()
     }
 }
 
@@ -155,11 +169,14 @@ pub enum DataType {
     List(Box<DataType>),
     Option(Box<DataType>),
     UserDefined(UserDefinedType),
+    /// Heap allocated in Rust to break a recursive type, the resolver inserts
+    /// it where needed.
+    Boxed(Box<DataType>),
 }
 
 impl DataType {
-    pub fn spatial_type(&self) -> String {
-        match self {
+    pub fn spatial_type(&self) -> Result<String, String> {
+        Ok(match self {
             Self::Bool => "bool".to_string(),
             Self::Float => "float".to_string(),
             Self::Bytes => "bytes".to_string(),
@@ -177,11 +194,12 @@ impl DataType {
             Self::SFixed64 => "sfixed64".to_string(),
             Self::EntityID => "EntityId".to_string(),
             Self::Entity => "Entity".to_string(),
-            Self::Map(fst, snd) => format!("map<{},{}>", fst.spatial_type(), snd.spatial_type()),
-            Self::List(fst) => format!("list<{}>", fst.spatial_type()),
-            Self::Option(fst) => format!("option<{}>", fst.spatial_type()),
-            Self::UserDefined(fst) => fst.spatial_type(),
-        }
+            Self::Map(fst, snd) => format!("map<{},{}>", fst.spatial_type()?, snd.spatial_type()?),
+            Self::List(fst) => format!("list<{}>", fst.spatial_type()?),
+            Self::Option(fst) => format!("option<{}>", fst.spatial_type()?),
+            Self::UserDefined(fst) => fst.spatial_type()?,
+            Self::Boxed(fst) => fst.spatial_type()?,
+        })
     }
 
     pub fn rust_type(&self) -> String {
@@ -191,17 +209,25 @@ impl DataType {
             Self::Uint64 => "u64".to_string(),
             Self::Int32 => "i32".to_string(),
             Self::Int64 => "i64".to_string(),
+            Self::SInt32 => "i32".to_string(),
+            Self::SInt64 => "i64".to_string(),
+            Self::Fixed32 => "u32".to_string(),
+            Self::Fixed64 => "u64".to_string(),
+            Self::SFixed32 => "i32".to_string(),
+            Self::SFixed64 => "i64".to_string(),
             Self::Float => "f32".to_string(),
             Self::Double => "f64".to_string(),
             Self::String => "String".to_string(),
             Self::Bytes => "Vec<u8>".to_string(),
+            Self::EntityID => "EntityId".to_string(),
+            Self::Entity => "Entity".to_string(),
             Self::Map(fst, snd) => {
                 format!("HashMap<{}, {}>", (*fst).rust_type(), (*snd).rust_type())
             }
             Self::List(fst) => format!("Vec<{}>", (*fst).rust_type()),
             Self::Option(fst) => format!("Option<{}>", (*fst).rust_type()),
             Self::UserDefined(fst) => fst.rust_type(),
-            _ => "uninmplemented()!".to_string(),
+            Self::Boxed(fst) => format!("Box<{}>", (*fst).rust_type()),
         }
     }
 }
@@ -211,11 +237,15 @@ pub enum ResolvedTypeKind {
     Enum,
     Type,
     Component,
+    /// The root `EntityId` type, or the type overriding it.
+    EntityId,
+    /// The root `Entity` type, or the type overriding it.
+    Entity,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum UserDefinedType {
-    Unresolved(String),
+    Unresolved(Vec<String>),
     Resolved(String, ResolvedTypeKind),
 }
 
@@ -226,21 +256,27 @@ impl From<&(String, ResolvedTypeKind)> for UserDefinedType {
 }
 
 impl UserDefinedType {
-    pub fn spatial_type(&self) -> String {
+    pub fn spatial_type(&self) -> Result<String, String> {
         match self {
-            Self::Unresolved(name) => panic!("{} is not resolved in the current schema", name),
+            Self::Unresolved(path) => Err(format!(
+                "{} is not resolved in the current schema",
+                path.join(".")
+            )),
             Self::Resolved(name, kind) => match kind {
-                ResolvedTypeKind::Enum => "enum",
-                ResolvedTypeKind::Type => "type",
-                ResolvedTypeKind::Component => panic!("You can't reference component {}", name),
-            }
-            .to_string(),
+                ResolvedTypeKind::Enum => Ok("enum".to_string()),
+                ResolvedTypeKind::Type => Ok("type".to_string()),
+                ResolvedTypeKind::EntityId => Ok("EntityId".to_string()),
+                ResolvedTypeKind::Entity => Ok("Entity".to_string()),
+                ResolvedTypeKind::Component => {
+                    Err(format!("You can't reference component {}", name))
+                }
+            },
         }
     }
 
     pub fn rust_type(&self) -> String {
         match self {
-            Self::Unresolved(name) => name.to_owned(),
+            Self::Unresolved(path) => path.join("::"),
             Self::Resolved(name, _) => name.to_owned(),
         }
     }
@@ -1,5 +1,13 @@
 use crate::ast::SchemaFile;
-use crate::{ast::ASTNode, resolver::resolve_types};
+use crate::{
+    ast::ASTNode,
+    config::Config,
+    error::Error,
+    output::{format_code, write_files, Formatting},
+    resolver::resolve_types_with_overrides,
+    validator::validate,
+};
+use quote::__private::TokenStream;
 use std::convert::TryFrom;
 use std::ffi::OsStr;
 use std::path::Path;
@@ -13,38 +21,58 @@ pub struct ASTBuilder {
 
 #[allow(dead_code)]
 impl ASTBuilder {
-    pub fn build(self) -> AST {
-        self.directories
+    /// Parses every schema under the directories, failing with all the
+    /// diagnostics collected if any file can't be read or parsed.
+    pub fn build(self) -> Result<AST, Error> {
+        let (schemas, errors) = self.load()?;
+        if errors.is_empty() {
+            AST::from_schemas(schemas)
+        } else {
+            Err(Error::multiple(errors))
+        }
+    }
+
+    /// Parses every schema under the directories, skipping the files that
+    /// can't be read or parsed. The errors of the skipped files are returned
+    /// alongside the partial AST.
+    pub fn build_lenient(self) -> Result<(AST, Vec<Error>), Error> {
+        let (schemas, errors) = self.load()?;
+        Ok((AST::from_schemas(schemas)?, errors))
+    }
+
+    fn load(self) -> Result<(Vec<SchemaFile>, Vec<Error>), Error> {
+        let (schemas, errors): (Vec<_>, Vec<_>) = self
+            .directories
             .into_iter()
             .map(|d| {
                 WalkDir::new(&d)
                     .follow_links(true)
                     .into_iter()
-                    .filter_map(|e| e.ok())
                     .map(|e| {
-                        e.path()
-                            .to_str()
-                            .map(|s| s.to_string())
-                            .ok_or("Can't tranform into &str")
+                        e.map_err(|e| {
+                            let path = e.path().unwrap_or(&d).to_path_buf();
+                            Error::io(path)(e.into())
+                        })
                     })
-                    .filter_map(Result::ok)
-                    .map(PathBuf::from)
-                    .filter(|p| p.extension() == Some(OsStr::new("schema")))
-                    .map(|p| (SchemaFile::try_from(p.clone()), p))
-                    .map(|(schemas, buf)| match schemas {
-                        Ok(data) => Ok(data),
-                        Err(e) => {
-                            eprintln!("{}: {:?}", e, buf);
-                            Err(())
-                        }
+                    .filter_map(|e| match e {
+                        Ok(e) => e.path().to_str().map(|s| Ok(PathBuf::from(s))),
+                        Err(e) => Some(Err(e)),
                     })
-                    .filter_map(Result::ok)
-                    .collect::<Vec<_>>()
+                    .filter(|p| match p {
+                        Ok(p) => p.extension() == Some(OsStr::new("schema")),
+                        Err(_) => true,
+                    })
+                    .map(|p| p.map(|p| SchemaFile::try_from(p).map(|s| s.relative_to(&d))))
+                    .collect::<Result<Vec<_>, _>>()
             })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
             .flatten()
-            .fold(AST::default(), |acc, val| {
-                acc.merge_schema(&val, &val.package_name)
-            })
+            .partition(Result::is_ok);
+        Ok((
+            schemas.into_iter().filter_map(Result::ok).collect(),
+            errors.into_iter().filter_map(Result::err).collect(),
+        ))
     }
 
     pub fn with_directory<P: AsRef<Path>>(mut self, path: P) -> Self {
@@ -54,39 +82,151 @@ impl ASTBuilder {
     }
 }
 
+const ROOT_TYPES: &str = "
+/// Identifier of an entity, used for `EntityId` fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct EntityId(pub i64);
+
+/// Components of a whole entity keyed by component ID, used for `Entity` fields.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Entity(pub std::collections::HashMap<u32, Vec<u8>>);
+
+/// Events of a component, the ones received kept apart from the ones queued
+/// to be sent with the next update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Events<T> {
+    pub received: Vec<T>,
+    pub queued: Vec<T>,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            received: Vec::new(),
+            queued: Vec::new(),
+        }
+    }
+}
+";
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Eq, PartialEq, Default)]
 pub struct AST {
     pub inner: Vec<ASTNode>,
 }
 
 impl AST {
-    pub fn generate<P: AsRef<Path> + Clone, S: AsRef<str>>(
+    /// Writes the generated code to `path`, which must be the directory of the
+    /// Rust module at the path `module`: `crate::schema`, `::schema_crate`, a
+    /// bare `schema` for `crate::schema`, or `self` when the generated code
+    /// should only refer to itself relatively and may be placed anywhere.
+    ///
+    /// Only the files whose contents changed are rewritten. Files generated
+    /// earlier that aren't needed anymore are removed, any other file in
+    /// `path` is left alone.
+    pub fn generate<P: AsRef<Path>, S: AsRef<str>>(self, path: P, module: S) -> Result<(), Error> {
+        self.generate_formatted(path, module, Formatting::default())
+    }
+
+    /// Same as `generate`, formatting the generated files as `formatting`
+    /// says.
+    pub fn generate_formatted<P: AsRef<Path>, S: AsRef<str>>(
         self,
         path: P,
         module: S,
-    ) -> Result<(), std::io::Error> {
-        let new_ast = resolve_types(self, module);
-        let path_clone = path.clone();
-        if path_clone.as_ref().exists() {
-            std::fs::remove_dir_all(path)?;
-        }
-        for node in &new_ast.inner {
-            node.generate_node(path_clone.clone())?;
-        }
-        ASTNode::generate_mod_rs(&new_ast.inner, path_clone)
+        formatting: Formatting,
+    ) -> Result<(), Error> {
+        self.write(&Config {
+            output: Some(path.as_ref().to_path_buf()),
+            module: module.as_ref().to_owned(),
+            rustfmt: formatting == Formatting::Rustfmt,
+            ..Config::default()
+        })
     }
 
-    fn merge_schema<T: AsRef<str>>(self, schema: &SchemaFile, path: &[T]) -> Self {
+    /// Validates the AST and resolves its types as `generate` would, without
+    /// generating anything.
+    pub fn check<S: AsRef<str>>(self, module: S) -> Result<(), Error> {
+        self.resolve(&Config {
+            module: module.as_ref().to_owned(),
+            ..Config::default()
+        })
+        .map(|_| ())
+    }
+
+    /// Validates and resolves the AST, then applies the package selection and
+    /// derives of `config`.
+    pub(crate) fn resolve(self, config: &Config) -> Result<AST, Error> {
+        validate(&self)?;
+        let ast = resolve_types_with_overrides(self, &config.module, &config.type_overrides)?;
+        config.apply(ast)
+    }
+
+    /// Writes the code generated as `config` says, see `generate`.
+    pub(crate) fn write(self, config: &Config) -> Result<(), Error> {
+        let path = config.output()?;
+        let module = config.module.as_str();
+        let new_ast = self.resolve(config)?;
+        let mut files = new_ast
+            .inner
+            .iter()
+            .map(|node| node.generate_node(path, module, config.formatting()))
+            .collect::<Result<Vec<_>, _>>()?
+            .concat();
+        let (mod_rs, exports) = ASTNode::generate_mod_rs(&new_ast.inner, path);
+        files.push((mod_rs, exports + ROOT_TYPES));
+        write_files(path, files)
+    }
+
+    /// Generates the code of the module at the path `module` as a single token
+    /// stream, with an inline `mod` for every package and schema file. Nothing
+    /// is written to disk, so a `build.rs` script can save it to `OUT_DIR` and
+    /// `include!` it.
+    pub fn generate_tokens<S: AsRef<str>>(self, module: S) -> Result<TokenStream, Error> {
+        let new_ast = self.resolve(&Config {
+            module: module.as_ref().to_owned(),
+            ..Config::default()
+        })?;
+        let modules = ASTNode::generate_modules(&new_ast.inner, module.as_ref())?;
+        let root_types = ROOT_TYPES
+            .parse::<TokenStream>()
+            .map_err(|e| Error::codegen(module.as_ref(), e))?;
+        Ok(quote! {
+            #modules
+            #root_types
+        })
+    }
+
+    /// Same as `generate_tokens`, as pretty-printed Rust source code.
+    pub fn generate_string<S: AsRef<str>>(self, module: S) -> Result<String, Error> {
+        let module = module.as_ref();
+        let tokens = self.generate_tokens(module)?;
+        format_code(tokens, Formatting::Pretty).map_err(|message| Error::codegen(module, message))
+    }
+
+    fn from_schemas(schemas: Vec<SchemaFile>) -> Result<Self, Error> {
+        schemas.into_iter().try_fold(AST::default(), |acc, val| {
+            acc.merge_schema(&val, &val.package_name)
+        })
+    }
+
+    fn merge_schema<T: AsRef<str>>(self, schema: &SchemaFile, path: &[T]) -> Result<Self, Error> {
+        if let Some(ASTNode::SchemaNode(root)) = self
+            .inner
+            .iter()
+            .find(|n| matches!(n, ASTNode::SchemaNode(_)))
+        {
+            // Only a schema without a package can sit at the root.
+            return Err(Error::MissingPackage {
+                file: root.path.clone(),
+            });
+        }
         if !path.is_empty() {
-            let is_path_present = self
-                .inner
-                .iter()
-                .map(|n| match n {
-                    ASTNode::SchemaNode(_) => panic!("SchemaFile shouldn't be at the root of AST"),
-                    ASTNode::PackageNode(pn) => pn.name == *path[0].as_ref(),
-                })
-                .fold(false, |acc, val| acc | val);
-            if is_path_present {
+            let is_path_present = self.inner.iter().any(|n| match n {
+                ASTNode::SchemaNode(_) => false,
+                ASTNode::PackageNode(pn) => pn.name == *path[0].as_ref(),
+            });
+            Ok(if is_path_present {
                 AST {
                     inner: self
                         .inner
@@ -98,9 +238,318 @@ impl AST {
                 let mut inner = self.inner;
                 inner.push(ASTNode::package_schema(schema, path));
                 AST { inner }
-            }
+            })
         } else {
-            panic!("SchemaFile does not have a package name");
+            Err(Error::MissingPackage {
+                file: schema.path.clone(),
+            })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn schema_directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("spatialos-codegen-{}-{}", name, std::process::id()));
+        if directory.exists() {
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+        for (path, source) in files {
+            let path = directory.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        directory
+    }
+
+    const SCHEMAS: &[(&str, &str)] = &[
+        ("game/health.schema", "package game; type Health {}"),
+        (
+            "game/broken.schema",
+            "package game; type Broken { uint32 hp }",
+        ),
+        ("game/typo.schema", "package game; typ Typo {}"),
+    ];
+
+    #[test]
+    fn test_build_fails_on_every_unparsable_schema() {
+        let directory = schema_directory("strict", SCHEMAS);
+        let result = ASTBuilder::default().with_directory(&directory).build();
+        std::fs::remove_dir_all(&directory).unwrap();
+        match result {
+            Err(Error::Multiple(errors)) => {
+                let mut files = errors
+                    .iter()
+                    .map(|e| match e {
+                        Error::Parse(diagnostic) => diagnostic.file.clone(),
+                        e => panic!("Unexpected error: {}", e),
+                    })
+                    .collect::<Vec<_>>();
+                files.sort();
+                assert_eq!(files.len(), 2);
+                assert!(files[0].ends_with("game/broken.schema"));
+                assert!(files[1].ends_with("game/typo.schema"));
+            }
+            result => panic!("Expected two parse errors, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_build_lenient_skips_unparsable_schemas() {
+        let directory = schema_directory("lenient", SCHEMAS);
+        let result = ASTBuilder::default()
+            .with_directory(&directory)
+            .build_lenient();
+        std::fs::remove_dir_all(&directory).unwrap();
+        let (ast, skipped) = result.unwrap();
+        assert_eq!(skipped.len(), 2);
+        match ast.inner.as_slice() {
+            [ASTNode::PackageNode(game)] => assert_eq!(
+                game.inner
+                    .iter()
+                    .map(|n| match n {
+                        ASTNode::SchemaNode(s) => s.name.as_str(),
+                        ASTNode::PackageNode(p) => p.name.as_str(),
+                    })
+                    .collect::<Vec<_>>(),
+                vec!["health"]
+            ),
+            inner => panic!("Unexpected AST: {:?}", inner),
+        }
+    }
+
+    #[test]
+    fn test_generate_propagates_errors() {
+        let generate = |name: &str, files: &[(&str, &str)]| {
+            let directory = schema_directory(name, files);
+            std::fs::create_dir_all(directory.join("game/unreadable.schema")).unwrap();
+            let output = directory.join("generated");
+            let result = Config {
+                schemas: vec![directory.clone()],
+                output: Some(output.clone()),
+                ..Config::default()
+            }
+            .generate();
+            let written = output.exists();
+            std::fs::remove_dir_all(&directory).unwrap();
+            assert!(!written, "{} wrote code despite failing", name);
+            result.unwrap_err()
+        };
+        match generate("unreadable", &[]) {
+            Error::Io { path, .. } => assert!(path.ends_with("game/unreadable.schema")),
+            e => panic!("Expected an IO error, got {}", e),
+        }
+        match generate(
+            "unparsable",
+            &[("game/typo.schema", "package game; typ Typo {}")],
+        ) {
+            Error::Multiple(errors) => match errors.as_slice() {
+                [Error::Io { .. }, Error::Parse(diagnostic)]
+                | [Error::Parse(diagnostic), Error::Io { .. }] => {
+                    assert!(diagnostic.file.ends_with("game/typo.schema"))
+                }
+                errors => panic!("Expected an IO and a parse error, got {:?}", errors),
+            },
+            e => panic!("Expected several errors, got {}", e),
+        }
+
+        let directory = schema_directory(
+            "unresolved",
+            &[(
+                "game/player.schema",
+                "package game; type Player { Health health = 1; }",
+            )],
+        );
+        let result = ASTBuilder::default()
+            .with_directory(&directory)
+            .build()
+            .and_then(|ast| ast.generate_tokens("generated"));
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "game/player.schema: game.Player.health: unable to resolve Health"
+        );
+    }
+
+    #[test]
+    fn test_merge_schema_rejects_schema_at_root() {
+        let schema = crate::parser::schema_file::parse_schema(
+            "game/health.schema",
+            b"package game; type Health {}",
+        )
+        .unwrap()
+        .with_name("health".to_owned())
+        .with_path("game/health.schema".to_owned())
+        .build()
+        .unwrap();
+        let ast = AST {
+            inner: vec![ASTNode::SchemaNode(schema.clone())],
+        };
+        match ast.merge_schema(&schema, &schema.package_name) {
+            Err(Error::MissingPackage { file }) => assert_eq!(file, schema.path),
+            result => panic!("Expected a missing package, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_generate_tokens_inline_modules() {
+        let directory = schema_directory(
+            "tokens",
+            &[
+                ("game/health.schema", "package game; type Health {}"),
+                (
+                    "game/player.schema",
+                    "package game; import \"game/health.schema\";
+                    component Player { id = 1; Health health = 1; }",
+                ),
+            ],
+        );
+        let result = ASTBuilder::default()
+            .with_directory(&directory)
+            .build()
+            .and_then(|ast| ast.generate_string("self"));
+        std::fs::remove_dir_all(&directory).unwrap();
+        let file = syn::parse_file(&result.unwrap()).unwrap();
+        let names = |items: &[syn::Item]| {
+            items
+                .iter()
+                .map(|item| match item {
+                    syn::Item::Mod(m) => format!("mod {}", m.ident),
+                    syn::Item::Use(u) => quote!(#u).to_string(),
+                    syn::Item::Struct(s) => format!("struct {}", s.ident),
+                    syn::Item::Impl(i) => {
+                        let ty = &i.self_ty;
+                        format!("impl {}", quote!(#ty))
+                    }
+                    item => panic!("Unexpected item: {}", quote!(#item)),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&file.items),
+            vec![
+                "mod game",
+                "struct EntityId",
+                "struct Entity",
+                "struct Events",
+                "impl Events < T >"
+            ]
+        );
+        match &file.items[0] {
+            syn::Item::Mod(syn::ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => {
+                let mut names = names(items);
+                names.sort();
+                assert_eq!(
+                    names,
+                    vec![
+                        "mod health",
+                        "mod player",
+                        "pub use health :: Health ;",
+                        "pub use player :: Player ;",
+                    ]
+                );
+            }
+            item => panic!("Expected an inline module, got {}", quote!(#item)),
+        }
+    }
+
+    #[test]
+    fn test_generate_root_types() {
+        let directory = schema_directory(
+            "root",
+            &[(
+                "game/spawner.schema",
+                "package game;
+                type Spawned { EntityId id = 1; }
+                component Spawner {
+                    id = 1;
+                    EntityId owner = 1;
+                    Entity template = 2;
+                    event Spawned spawned;
+                }",
+            )],
+        );
+        let result = ASTBuilder::default()
+            .with_directory(&directory)
+            .build()
+            .and_then(|ast| ast.generate_string("crate::generated"));
+        std::fs::remove_dir_all(&directory).unwrap();
+        let file = syn::parse_file(&result.unwrap()).unwrap();
+        let fields = |fields: &syn::Fields| {
+            fields
+                .iter()
+                .map(|f| {
+                    let ty = &f.ty;
+                    match &f.ident {
+                        Some(ident) => format!("{}: {}", ident, quote!(#ty)),
+                        None => quote!(#ty).to_string(),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let structs = |items: &[syn::Item]| {
+            items
+                .iter()
+                .filter_map(|item| match item {
+                    syn::Item::Struct(s) => Some((s.ident.to_string(), fields(&s.fields))),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let module = |items: &[syn::Item], name: &str| -> Vec<syn::Item> {
+            items
+                .iter()
+                .find_map(|item| match item {
+                    syn::Item::Mod(syn::ItemMod {
+                        ident,
+                        content: Some((_, items)),
+                        ..
+                    }) if ident == name => Some(items.clone()),
+                    _ => None,
+                })
+                .unwrap_or_else(|| panic!("Expected the module {}", name))
+        };
+        let own = |name: &str, fields: &[&str]| {
+            (
+                name.to_owned(),
+                fields.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(
+            structs(&file.items),
+            vec![
+                own("EntityId", &["i64"]),
+                own(
+                    "Entity",
+                    &["std :: collections :: HashMap < u32 , Vec < u8 > >"]
+                ),
+                own("Events", &["received: Vec < T >", "queued: Vec < T >"]),
+            ]
+        );
+        let spawner = module(&module(&file.items, "game"), "spawner");
+        assert!(!spawner.iter().any(|item| matches!(item, syn::Item::Use(u)
+            if quote!(#u).to_string().contains("EntityId"))));
+        assert_eq!(
+            structs(&spawner),
+            vec![
+                own("Spawned", &["id: crate :: generated :: EntityId"]),
+                own(
+                    "Spawner",
+                    &[
+                        "owner: crate :: generated :: EntityId",
+                        "template: crate :: generated :: Entity",
+                        "spawned: crate :: generated :: Events < SpawnerSpawnedEvent >",
+                    ]
+                ),
+            ]
+        );
+    }
+}
@@ -2,32 +2,39 @@ use quote::__private::TokenStream;
 
 use crate::{
     ast::{Component, Enum, Type},
-    codegen::Generator,
+    codegen::{module_file_name, root_alias, root_path, Generator},
+    diagnostic::Diagnostic,
+    error::Error,
+    output::{format_code, Formatting, OutputFile, HEADER},
     parser::schema_file::parse_schema,
 };
 use std::{
     convert::TryFrom,
     fs::File,
-    io::{Read, Write},
-    path::{Path, PathBuf},
-    process::Command,
+    io::Read,
+    path::{self, Path, PathBuf},
 };
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct SchemaFile {
     pub package_name: Vec<String>,
     pub name: String,
+    pub path: String,
+    pub imports: Vec<String>,
     pub types: Vec<Type>,
     pub enums: Vec<Enum>,
     pub components: Vec<Component>,
 }
 
 impl SchemaFile {
-    fn generate(&self) -> TokenStream {
-        let enums = Enum::generate_multiple(&self.enums);
-        let types = Type::generate_multiple(&self.types);
-        let components = Component::generate_multiple(&self.components);
-        quote! {
+    pub(crate) fn generate(&self, module: &str) -> Result<TokenStream, Error> {
+        let enums = Enum::generate_multiple(&self.enums)?;
+        let types = Type::generate_multiple(&self.types)?;
+        let components = Component::generate_multiple(&self.components, &root_path(module)?)?;
+        let root_alias = root_alias(module, self.package_name.len() + 1)?;
+        Ok(quote! {
+            #root_alias
+
             #[allow(unused_imports)]
             use std::collections::HashMap;
 
@@ -39,7 +46,7 @@ impl SchemaFile {
             #types
 
             #components
-        }
+        })
     }
 
     pub fn get_exports(&self) -> Vec<String> {
@@ -50,37 +57,86 @@ impl SchemaFile {
         exports
     }
 
-    pub fn generate_schema<P: AsRef<Path> + Clone>(&self, path: P) -> Result<(), std::io::Error> {
-        std::fs::create_dir_all(path.clone()).map(|_| {
-            let mut file = File::create(path.clone().as_ref().join(self.name.clone() + ".rs"))?;
-            write!(&mut file, "{}", self.generate())?;
-            Command::new("rustfmt")
-                .arg(path.clone().as_ref().join(self.name.clone() + ".rs"))
-                .output()
-                .expect("Failed to execute rustfmt");
-            Ok(())
-        })?
+    pub fn relative_to<P: AsRef<Path>>(mut self, root: P) -> Self {
+        if let Ok(relative) = Path::new(&self.path).strip_prefix(root) {
+            self.path = schema_path(relative);
+        }
+        self
+    }
+
+    /// The file of this schema when generated in the directory `path`.
+    pub fn generate_schema<P: AsRef<Path>>(
+        &self,
+        path: P,
+        module: &str,
+        formatting: Formatting,
+    ) -> Result<OutputFile, Error> {
+        let code = self.generate(module).map_err(|e| e.in_file(&self.path))?;
+        let file_path = path.as_ref().join(module_file_name(&self.name) + ".rs");
+        let code = format_code(code, formatting).map_err(|message| Error::Format {
+            path: file_path.clone(),
+            message,
+        })?;
+        Ok((file_path, HEADER.to_owned() + &code))
     }
 }
 
+/// `path` with `/` separators, keeping any root or prefix it starts with.
+fn schema_path<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref()
+        .components()
+        .fold(String::new(), |mut acc, component| {
+            match component {
+                path::Component::Prefix(prefix) => {
+                    acc.push_str(&prefix.as_os_str().to_string_lossy())
+                }
+                path::Component::RootDir => acc.push('/'),
+                component => {
+                    if !acc.is_empty() && !acc.ends_with('/') {
+                        acc.push('/');
+                    }
+                    acc.push_str(&component.as_os_str().to_string_lossy());
+                }
+            }
+            acc
+        })
+}
+
 impl TryFrom<PathBuf> for SchemaFile {
-    type Error = String;
+    type Error = Error;
 
     fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
         let filename = path
             .file_stem()
-            .ok_or("Unable to get file stem")
-            .map(|s| s.to_str())?
-            .ok_or("Can't convert file stem to UTF-8")
-            .map(|s| s.to_string())?;
-        let mut file = File::open(path).map_err(|e| format!("Unable to open file: {}", e))?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .map_err(|e| format!("Unable to read file: {}", e))?;
-        parse_schema(contents.as_bytes())
-            .map(|r| r.1)
-            .map_err(|e| format!("Unable to parse data: {}", e))
-            .map(|sb| sb.with_name(filename).build())?
-            .map_err(|e| format!("Cannot convert SchemaFile: {}", e))
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let schema_path = schema_path(&path);
+        let mut contents = Vec::new();
+        File::open(&path)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .map_err(Error::io(&path))?;
+        let builder = parse_schema(&schema_path, &contents)
+            .map_err(|d| Error::multiple(d.into_iter().map(Error::Parse).collect()))?;
+        builder
+            .with_name(filename)
+            .with_path(schema_path.clone())
+            .build()
+            .map_err(|e| Error::Parse(Diagnostic::new(&schema_path, &contents, &contents, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_schema_path() {
+        assert_eq!(schema_path("game/health.schema"), "game/health.schema");
+        assert_eq!(schema_path("./game/health.schema"), "./game/health.schema");
+        assert_eq!(
+            schema_path("/tmp/game/health.schema"),
+            "/tmp/game/health.schema"
+        );
     }
 }
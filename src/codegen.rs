@@ -1,219 +1,906 @@
 use quote::__private::TokenStream;
 
-use crate::ast::{Command, Component, Enum, Member, Type, Variant};
+use crate::{
+    ast::{
+        Annotation, AnnotationValue, Command, Component, DataType, Enum, Event, Member, Type,
+        Variant,
+    },
+    error::Error,
+};
+
+impl Annotation {
+    /// `[RustAttribute("...")]` is forwarded verbatim as an attribute on the
+    /// generated item, other annotations are only kept in the AST.
+    fn rust_attribute(&self) -> Result<Option<TokenStream>, Error> {
+        match (self.name.last(), self.values.as_slice()) {
+            (Some(name), [AnnotationValue::String(attribute)]) if name == "RustAttribute" => {
+                let attribute = attribute.replace("\\\"", "\"");
+                let meta = syn::parse_str::<syn::Meta>(&attribute).map_err(|e| {
+                    Error::codegen("", format!("invalid RustAttribute `{}`: {}", attribute, e))
+                })?;
+                Ok(Some(quote! { #[#meta] }))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn rust_attributes(data: &[Self]) -> Result<Vec<TokenStream>, Error> {
+        data.iter()
+            .filter_map(|a| a.rust_attribute().transpose())
+            .collect()
+    }
+}
+
+impl DataType {
+    fn syn_type<S: AsRef<str>>(&self, declaration: S) -> Result<syn::Type, Error> {
+        let rust_type = self.rust_type();
+        syn::parse_str::<syn::Type>(&rust_type).map_err(|e| {
+            Error::codegen(
+                declaration,
+                format!("invalid Rust type `{}`: {}", rust_type, e),
+            )
+        })
+    }
+
+    fn spatial_type_of<S: AsRef<str>>(&self, declaration: S) -> Result<String, Error> {
+        self.spatial_type()
+            .map_err(|message| Error::codegen(declaration, message))
+    }
+}
 
 impl Command {
-    fn get_member<S: AsRef<str>>(&self, component_name: S) -> TokenStream {
-        let name = format_ident!("{}", self.name);
-        let command_name = format_ident!(
+    fn get_type_names<S: AsRef<str>>(&self, component_name: S) -> (syn::Ident, syn::Ident) {
+        let command_name = format!(
             "{}{}",
             component_name.as_ref(),
             to_upper_camel_case(&self.name)
         );
+        (
+            format_ident!("{}Request", command_name),
+            format_ident!("{}Response", command_name),
+        )
+    }
+
+    fn get_member<S: AsRef<str>>(&self, component_name: S, index: u32) -> TokenStream {
+        let name = rust_ident(&self.name);
+        let field_name = field_name(&self.name);
+        let (request, response) = self.get_type_names(component_name);
+        let comments = &self.comments;
         quote! {
-            #name: PhantomData<#command_name>
+            #(#[doc = #comments])*
+            #field_name
+            #[command_index(#index)]
+            #name: PhantomData<(#request, #response)>
         }
     }
 
-    fn get_types<S: AsRef<str>>(&self, component_name: S) -> TokenStream {
-        let command_name = format_ident!(
-            "{}{}",
+    fn get_types<S: AsRef<str>>(&self, component_name: S) -> Result<TokenStream, Error> {
+        let (request, response) = self.get_type_names(component_name);
+        let arg_types = self
+            .args
+            .iter()
+            .map(|arg| arg.syn_type(&self.name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let request_args = match arg_types.as_slice() {
+            [arg_type] => quote! { #arg_type },
+            _ => quote! { (#(#arg_types),*) },
+        };
+        let response_args = self.r_type.syn_type(&self.name)?;
+        let allow = allow_non_camel_case(&request.to_string());
+        Ok(quote! {
+            #allow
+            pub type #request = #request_args;
+            #allow
+            pub type #response = #response_args;
+        })
+    }
+
+    fn get_impl(&self, index: u32) -> TokenStream {
+        let index_name = format_ident!("{}_COMMAND_INDEX", self.name.to_uppercase());
+        quote! {
+            pub const #index_name: u32 = #index;
+        }
+    }
+
+    fn get_handler<S: AsRef<str>>(&self, component_name: S) -> TokenStream {
+        let name = rust_ident(&self.name);
+        let (request, response) = self.get_type_names(component_name);
+        let comments = &self.comments;
+        quote! {
+            #(#[doc = #comments])*
+            fn #name(&mut self, request: #request) -> #response;
+        }
+    }
+}
+
+impl Event {
+    fn get_type_name<S: AsRef<str>>(&self, component_name: S) -> syn::Ident {
+        format_ident!(
+            "{}{}Event",
             component_name.as_ref(),
             to_upper_camel_case(&self.name)
-        );
-        let request = format_ident!("{}Request", command_name);
-        let response = format_ident!("{}Response", command_name);
-        let request_args = if self.args.len() > 1 {
-            let arg_types = self
-                .args
-                .iter()
-                .map(|arg| arg.rust_type())
-                .map(|s| syn::parse_str::<syn::Type>(&s).expect("Can't parse type"))
-                .collect::<Vec<_>>();
-            quote! {
-                (#(#arg_types),*)
+        )
+    }
+
+    /// The field of the event, whose `Events` type lives at `events`.
+    fn get_member<S: AsRef<str>>(
+        &self,
+        component_name: S,
+        index: u32,
+        events: &syn::Path,
+    ) -> Result<TokenStream, Error> {
+        let name = rust_ident(&self.name);
+        let field_name = field_name(&self.name);
+        let event_type = self.get_type_name(component_name);
+        let spatial_type = self.r_type.spatial_type_of(&self.name)?;
+        let comments = &self.comments;
+        Ok(quote! {
+            #(#[doc = #comments])*
+            #field_name
+            #[event_index(#index)]
+            #[spatial_type(#spatial_type)]
+            #name: #events<#event_type>
+        })
+    }
+
+    fn get_types<S: AsRef<str>>(&self, component_name: S) -> Result<TokenStream, Error> {
+        let event_type = self.get_type_name(component_name);
+        let payload = self.r_type.syn_type(&self.name)?;
+        let allow = allow_non_camel_case(&event_type.to_string());
+        Ok(quote! {
+            #allow
+            pub type #event_type = #payload;
+        })
+    }
+    fn get_impl<S: AsRef<str>>(&self, component_name: S, index: u32) -> TokenStream {
+        let name = rust_ident(&self.name);
+        let event_type = self.get_type_name(component_name);
+        let index_name = format_ident!("{}_EVENT_INDEX", self.name.to_uppercase());
+        let queue = format_ident!("queue_{}", self.name);
+        let take_received = format_ident!("take_received_{}", self.name);
+        let take_queued = format_ident!("take_queued_{}", self.name);
+        quote! {
+            pub const #index_name: u32 = #index;
+
+            /// Queues an event to be sent with the next component update.
+            pub fn #queue(&mut self, event: #event_type) {
+                self.#name.queued.push(event);
             }
-        } else {
-            let resolved = self
-                .args
-                .first()
-                .expect("Command must have at least one argument");
-            let arg_type =
-                syn::parse_str::<syn::Type>(&resolved.rust_type()).expect("Can't parse type");
-            quote! {
-                #arg_type
+
+            /// Takes the events received since the last call.
+            pub fn #take_received(&mut self) -> Vec<#event_type> {
+                std::mem::take(&mut self.#name.received)
+            }
+
+            /// Takes the events queued since the last call, to send them.
+            pub fn #take_queued(&mut self) -> Vec<#event_type> {
+                std::mem::take(&mut self.#name.queued)
             }
-        };
-        let response_args =
-            syn::parse_str::<syn::Type>(&self.r_type.rust_type()).expect("Can't parse type");
-        quote! {
-            type #request = #request_args;
-            type #response = #response_args;
-            type #command_name = Fn(#request) -> #response;
         }
     }
 }
 
-impl Generator for Component {
-    fn generate_one(&self) -> TokenStream {
-        let enums = <Enum as Generator>::generate_multiple(&self.enums);
-        let types = <Type as Generator>::generate_multiple(&self.types);
-        let members = <Member as Generator>::generate_multiple(&self.members);
-        let commands = self
-            .commands
+impl Component {
+    /// The code of the components of a schema file, generated under the root
+    /// module at the path `root`.
+    pub(crate) fn generate_multiple(data: &[Self], root: &str) -> Result<TokenStream, Error> {
+        let events = syn::parse_str::<syn::Path>(&format!("{}::Events", root))
+            .map_err(|e| Error::codegen("", format!("invalid root path `{}`: {}", root, e)))?;
+        let ones = data
             .iter()
-            .map(|c| c.get_member(&self.name))
-            .collect::<Vec<_>>();
+            .map(|comp| {
+                comp.generate_component(&events)
+                    .map_err(|e| e.within(&comp.name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(quote! {
+            #(#ones)*
+        })
+    }
+}
+
+/// Places the types and enums nested in the item `name` in their own module.
+fn generate_nested(name: &str, types: &[Type], enums: &[Enum]) -> Result<TokenStream, Error> {
+    if types.is_empty() && enums.is_empty() {
+        return Ok(quote! {});
+    }
+    let enums = <Enum as Generator>::generate_multiple(enums)?;
+    let types = <Type as Generator>::generate_multiple(types)?;
+    let module = format_ident!("{}", nested_module_name(name));
+    Ok(quote! {
+        pub mod #module {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #enums
+
+            #types
+        }
+    })
+}
+
+impl Component {
+    fn generate_component(&self, events_type: &syn::Path) -> Result<TokenStream, Error> {
+        let nested = generate_nested(&self.name, &self.types, &self.enums)?;
+        let events = (1..).zip(&self.events).collect::<Vec<_>>();
+        let commands = (1..).zip(&self.commands).collect::<Vec<_>>();
+        let fields = self
+            .members
+            .iter()
+            .map(Member::generate_one)
+            .chain(
+                events
+                    .iter()
+                    .map(|(i, e)| e.get_member(&self.name, *i, events_type)),
+            )
+            .chain(
+                commands
+                    .iter()
+                    .map(|(i, c)| Ok(c.get_member(&self.name, *i))),
+            )
+            .collect::<Result<Vec<_>, _>>()?;
 
         let commands_types = self
             .commands
             .iter()
             .map(|c| c.get_types(&self.name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let events_types = self
+            .events
+            .iter()
+            .map(|e| e.get_types(&self.name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let impls = events
+            .iter()
+            .map(|(i, e)| e.get_impl(&self.name, *i))
+            .chain(commands.iter().map(|(i, c)| c.get_impl(*i)))
+            .collect::<Vec<_>>();
+        let handlers = self
+            .commands
+            .iter()
+            .map(|c| c.get_handler(&self.name))
             .collect::<Vec<_>>();
         let comments = &self.comments;
+        let attributes = Annotation::rust_attributes(&self.annotations)?;
         let id = &self.id;
-        let name = format_ident!("{}", &self.name);
+        let name = rust_ident(&self.name);
+        let allow = allow_non_camel_case(&self.name);
 
-        let comma = if commands.len() > 0 && members.to_string().len() > 0 {
-            quote! { , }
+        let implementation = if impls.is_empty() {
+            quote! {}
         } else {
+            quote! {
+                impl #name {
+                    #(#impls)*
+                }
+            }
+        };
+        let handler = if handlers.is_empty() {
             quote! {}
+        } else {
+            let handler_name = format_ident!("{}CommandHandler", &self.name);
+            quote! {
+                /// Implemented by workers handling the commands of this component.
+                #allow
+                pub trait #handler_name {
+                    #(#handlers)*
+                }
+            }
         };
-        quote! {
-            #enums
-
-            #types
+        Ok(quote! {
+            #nested
 
             #[allow(dead_code)]
+            #allow
             #(#[doc = #comments])*
             #[derive(SpatialComponent)]
+            #(#attributes)*
             #[id(#id)]
             pub struct #name {
-                #members
+                #(#fields),*
+            }
 
-                #comma
+            #implementation
 
-                #(#commands),*
-            }
+            #(#events_types)*
 
             #(#commands_types)*
-        }
-    }
 
-    fn generate_multiple(data: &[Self]) -> TokenStream {
-        let ones: Vec<_> = data.iter().map(Self::generate_one).collect();
-        quote! {
-            #(#ones)*
-        }
+            #handler
+        })
     }
 }
 
 impl Generator for Enum {
-    fn generate_one(&self) -> TokenStream {
+    fn generate_one(&self) -> Result<TokenStream, Error> {
         let comments = &self.comments;
-        let name = format_ident!("{}", &self.name);
-        let variants = <Variant as Generator>::generate_multiple(&self.variants);
-        quote! {
+        let attributes =
+            Annotation::rust_attributes(&self.annotations).map_err(|e| e.within(&self.name))?;
+        let name = rust_ident(&self.name);
+        let allow = allow_non_camel_case(&self.name);
+        let variants = <Variant as Generator>::generate_multiple(&self.variants)
+            .map_err(|e| e.within(&self.name))?;
+        Ok(quote! {
+            #allow
             #(#[doc = #comments])*
             #[derive(SpatialEnum, Debug, Clone)]
+            #(#attributes)*
             pub enum #name {
                 #variants
             }
 
-        }
+        })
     }
 
-    fn generate_multiple(data: &[Self]) -> TokenStream {
-        let ones: Vec<_> = data.iter().map(Self::generate_one).collect();
-        quote! {
+    fn generate_multiple(data: &[Self]) -> Result<TokenStream, Error> {
+        let ones = data
+            .iter()
+            .map(Self::generate_one)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(quote! {
             #(#ones)*
-        }
+        })
     }
 }
 
 impl Generator for Member {
-    fn generate_one(&self) -> TokenStream {
+    fn generate_one(&self) -> Result<TokenStream, Error> {
         let docs = &self.comments;
+        let attributes =
+            Annotation::rust_attributes(&self.annotations).map_err(|e| e.within(&self.name))?;
         let id = self.id;
-        let spatial_type = self.m_type.spatial_type();
-        let rust_type =
-            syn::parse_str::<syn::Type>(&self.m_type.rust_type()).expect("Can't parse type");
-        let name = format_ident!("{}", &self.name);
-        quote! {
+        let spatial_type = self.m_type.spatial_type_of(&self.name)?;
+        let rust_type = self.m_type.syn_type(&self.name)?;
+        let name = rust_ident(&self.name);
+        let field_name = field_name(&self.name);
+        Ok(quote! {
             #(#[doc = #docs])*
+            #(#attributes)*
+            #field_name
             #[field_id(#id)]
             #[spatial_type(#spatial_type)]
             #name: #rust_type
-        }
+        })
     }
 }
 
 pub(crate) trait Generator: Sized {
-    fn generate_one(&self) -> TokenStream;
-    fn generate_multiple(data: &[Self]) -> TokenStream {
-        let ones: Vec<_> = data.iter().map(Self::generate_one).collect();
-        quote! {
+    fn generate_one(&self) -> Result<TokenStream, Error>;
+    fn generate_multiple(data: &[Self]) -> Result<TokenStream, Error> {
+        let ones = data
+            .iter()
+            .map(Self::generate_one)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(quote! {
             #(#ones),*
-        }
+        })
     }
 }
 
 impl Generator for Type {
-    fn generate_one(&self) -> TokenStream {
-        let enums = <Enum as Generator>::generate_multiple(&self.enums);
-        let types = <Type as Generator>::generate_multiple(&self.types);
-        let members = <Member as Generator>::generate_multiple(&self.members);
-        let comments = &self.comments;
-        let name = format_ident!("{}", &self.name);
-        quote! {
-            #enums
+    fn generate_one(&self) -> Result<TokenStream, Error> {
+        self.generate_type().map_err(|e| e.within(&self.name))
+    }
 
-            #types
+    fn generate_multiple(data: &[Self]) -> Result<TokenStream, Error> {
+        let ones = data
+            .iter()
+            .map(Self::generate_one)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(quote! {
+            #(#ones)*
+        })
+    }
+}
+
+impl Type {
+    fn generate_type(&self) -> Result<TokenStream, Error> {
+        let nested = generate_nested(&self.name, &self.types, &self.enums)?;
+        let members = <Member as Generator>::generate_multiple(&self.members)?;
+        let comments = &self.comments;
+        let attributes = Annotation::rust_attributes(&self.annotations)?;
+        let name = rust_ident(&self.name);
+        let allow = allow_non_camel_case(&self.name);
+        Ok(quote! {
+            #nested
 
             #[allow(dead_code)]
+            #allow
             #(#[doc = #comments])*
             #[derive(SpatialType)]
+            #(#attributes)*
             pub struct #name {
                 #members
             }
 
-        }
+        })
     }
+}
 
-    fn generate_multiple(data: &[Self]) -> TokenStream {
-        let ones: Vec<_> = data.iter().map(Self::generate_one).collect();
-        quote! {
-            #(#ones)*
+/// What generated files call the generated root module when it's placed at a
+/// relative module path.
+const ROOT_ALIAS: &str = "schema_root";
+
+fn module_segments(module: &str) -> Result<Vec<String>, Error> {
+    let path = syn::parse_str::<syn::Path>(module).map_err(|e| Error::InvalidModulePath {
+        module: module.to_owned(),
+        message: e.to_string(),
+    })?;
+    let segments = path
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect::<Vec<_>>();
+    Ok(if path.leading_colon.is_some() {
+        std::iter::once(String::new()).chain(segments).collect()
+    } else {
+        segments
+    })
+}
+
+fn is_relative(segments: &[String]) -> bool {
+    matches!(
+        segments.first().map(String::as_str),
+        Some("self") | Some("super")
+    )
+}
+
+/// The prefix of the paths to generated types, for code generated at the
+/// module path `module`. `crate::` and `::crate_name::` paths are used as
+/// they are, and a bare module name like `generated` stands for
+/// `crate::generated`. Paths starting with `self` or `super` are relative to
+/// the generated root module, which every file then reaches through an alias.
+pub(crate) fn root_path(module: &str) -> Result<String, Error> {
+    let segments = module_segments(module)?;
+    Ok(if is_relative(&segments) {
+        ROOT_ALIAS.to_owned()
+    } else if segments.len() == 1 && segments[0] != "crate" {
+        format!("crate::{}", module)
+    } else {
+        segments.join("::")
+    })
+}
+
+/// Imports the generated root module placed at the relative module path
+/// `module` into a file `depth` modules below it.
+pub(crate) fn root_alias(module: &str, depth: usize) -> Result<Option<TokenStream>, Error> {
+    let segments = module_segments(module)?;
+    if !is_relative(&segments) {
+        return Ok(None);
+    }
+    let path = (0..depth)
+        .map(|_| "super")
+        .chain(
+            segments
+                .iter()
+                .map(String::as_str)
+                .skip_while(|s| *s == "self"),
+        )
+        .map(|s| format_ident!("{}", s));
+    let alias = format_ident!("{}", ROOT_ALIAS);
+    Ok(Some(quote! {
+        #[allow(unused_imports)]
+        use #(#path)::* as #alias;
+    }))
+}
+
+/// Keywords that are only usable as fields or items as raw identifiers.
+const RAW_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can't even be raw identifiers.
+const RENAMED_KEYWORDS: &[&str] = &["_", "crate", "self", "Self", "super"];
+
+/// The Rust spelling of a schema identifier: keywords become raw identifiers,
+/// and the few that can't be raw get a trailing underscore.
+pub(crate) fn rust_name(name: &str) -> String {
+    if RAW_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else if RENAMED_KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+/// The name of the file or directory holding the module for a schema
+/// identifier, which has no `r#` even when the module name is raw.
+pub(crate) fn module_file_name(name: &str) -> String {
+    rust_name(name).trim_start_matches("r#").to_owned()
+}
+
+/// The module holding the types and enums nested in the item `name`, named
+/// after it in snake case: `ComponentInterest` nests in `component_interest`.
+/// An item already named in snake case gets a trailing underscore so the
+/// module doesn't clash with it.
+pub(crate) fn nested_module_name(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut module = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let starts_word = c.is_uppercase()
+            && match previous {
+                Some(p) if p.is_lowercase() || p.is_ascii_digit() => true,
+                Some(p) if p.is_uppercase() => next.is_some_and(|n| n.is_lowercase()),
+                _ => false,
+            };
+        if starts_word && !module.ends_with('_') {
+            module.push('_');
         }
+        module.extend(c.to_lowercase());
+    }
+    if module == name {
+        module.push('_');
+    }
+    rust_name(&module)
+}
+
+pub(crate) fn rust_ident(name: &str) -> syn::Ident {
+    format_ident!("{}", rust_name(name))
+}
+
+/// Schema types keep their name in Rust, so one that isn't in upper camel
+/// case, like `type`, has the lint about it silenced.
+fn allow_non_camel_case(name: &str) -> Option<TokenStream> {
+    let name = name.trim_start_matches("r#").trim_matches('_');
+    if name.starts_with(char::is_lowercase) || name.contains('_') {
+        Some(quote! { #[allow(non_camel_case_types)] })
+    } else {
+        None
+    }
+}
+
+/// Keeps the schema name of a field whose Rust name had to be escaped.
+fn field_name(name: &str) -> Option<TokenStream> {
+    if rust_name(name) == name {
+        None
+    } else {
+        Some(quote! { #[field_name(#name)] })
     }
 }
 
 fn capitalize(s: &str) -> String {
     let mut chars = s.chars();
-    format!(
-        "{}{}",
-        chars.next().unwrap().to_uppercase().collect::<String>(),
-        chars.collect::<String>()
-    )
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
 }
 
-fn to_upper_camel_case<S: AsRef<str>>(s: S) -> String {
-    s.as_ref()
+/// Converts a schema identifier to an upper camel case Rust identifier,
+/// prefixing it with `V` when nothing but digits or underscores would be left
+/// to start it.
+pub(crate) fn to_upper_camel_case<S: AsRef<str>>(s: S) -> String {
+    let name = s
+        .as_ref()
         .to_lowercase()
-        .split("_")
+        .split('_')
         .map(capitalize)
-        .fold(String::new(), |acc, val| acc + &val)
+        .collect::<String>();
+    match name.chars().next() {
+        Some(c) if c.is_alphabetic() => name,
+        _ => format!("V{}", name),
+    }
 }
 
 impl Generator for Variant {
-    fn generate_one(&self) -> TokenStream {
+    fn generate_one(&self) -> Result<TokenStream, Error> {
         let comments = &self.comments;
+        let attributes =
+            Annotation::rust_attributes(&self.annotations).map_err(|e| e.within(&self.name))?;
         let id = &self.id;
-        let name = format_ident!("{}", to_upper_camel_case(&self.name));
-        quote! {
+        let name = rust_ident(&to_upper_camel_case(&self.name));
+        Ok(quote! {
             #(#[doc = #comments])*
+            #(#attributes)*
             #[value(#id)]
             #name
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::ast::DataType;
+
+    fn event() -> Event {
+        Event {
+            name: "rabbit_born".to_owned(),
+            r_type: DataType::Uint32,
+            comments: vec![" A new rabbit".to_owned()],
         }
     }
+
+    #[test]
+    fn test_event_member() {
+        assert_eq!(
+            event()
+                .get_member("Farm", 2, &syn::parse_quote!(crate::generated::Events))
+                .unwrap()
+                .to_string(),
+            quote! {
+                #[doc = " A new rabbit"]
+                #[event_index(2u32)]
+                #[spatial_type("uint32")]
+                rabbit_born: crate::generated::Events<FarmRabbitBornEvent>
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_event_types() {
+        assert_eq!(
+            event().get_types("Farm").unwrap().to_string(),
+            quote! {
+                pub type FarmRabbitBornEvent = u32;
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_event_impl() {
+        assert_eq!(
+            event().get_impl("Farm", 2).to_string(),
+            quote! {
+                pub const RABBIT_BORN_EVENT_INDEX: u32 = 2u32;
+
+                /// Queues an event to be sent with the next component update.
+                pub fn queue_rabbit_born(&mut self, event: FarmRabbitBornEvent) {
+                    self.rabbit_born.queued.push(event);
+                }
+
+                /// Takes the events received since the last call.
+                pub fn take_received_rabbit_born(&mut self) -> Vec<FarmRabbitBornEvent> {
+                    std::mem::take(&mut self.rabbit_born.received)
+                }
+
+                /// Takes the events queued since the last call, to send them.
+                pub fn take_queued_rabbit_born(&mut self) -> Vec<FarmRabbitBornEvent> {
+                    std::mem::take(&mut self.rabbit_born.queued)
+                }
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_invalid_root_path() {
+        let error = Component::generate_multiple(&[], "crate::1bad").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("invalid root path `crate::1bad`: "));
+        assert!(error
+            .within("Health")
+            .in_file("game/health.schema")
+            .to_string()
+            .starts_with("game/health.schema: Health: invalid root path"));
+    }
+
+    #[test]
+    fn test_event_impl_distinct_methods() {
+        let methods = |name: &str| {
+            let event = Event {
+                name: name.to_owned(),
+                ..event()
+            };
+            let items: syn::File = syn::parse2(event.get_impl("Farm", 0)).unwrap();
+            items
+                .items
+                .into_iter()
+                .filter_map(|item| match item {
+                    syn::Item::Fn(f) => Some(f.sig.ident.to_string()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let (born, queued_born) = (methods("born"), methods("queued_born"));
+        assert!(born.iter().all(|m| !queued_born.contains(m)));
+    }
+
+    #[test]
+    fn test_command_types() {
+        let command = |args| Command {
+            name: "count_rabbits".to_owned(),
+            r_type: DataType::Uint32,
+            args,
+            comments: vec![],
+        };
+        assert_eq!(
+            command(vec![]).get_types("Farm").unwrap().to_string(),
+            quote! {
+                pub type FarmCountRabbitsRequest = ();
+                pub type FarmCountRabbitsResponse = u32;
+            }
+            .to_string()
+        );
+        assert_eq!(
+            command(vec![DataType::Bool])
+                .get_types("Farm")
+                .unwrap()
+                .to_string(),
+            quote! {
+                pub type FarmCountRabbitsRequest = bool;
+                pub type FarmCountRabbitsResponse = u32;
+            }
+            .to_string()
+        );
+        assert_eq!(
+            command(vec![DataType::Bool, DataType::String])
+                .get_types("Farm")
+                .unwrap()
+                .to_string(),
+            quote! {
+                pub type FarmCountRabbitsRequest = (bool, String);
+                pub type FarmCountRabbitsResponse = u32;
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_to_upper_camel_case() {
+        assert_eq!(to_upper_camel_case("LEVEL_2"), "Level2");
+        assert_eq!(to_upper_camel_case("HTTP_REQUEST"), "HttpRequest");
+        assert_eq!(to_upper_camel_case("took__damage_"), "TookDamage");
+        assert_eq!(to_upper_camel_case("_1"), "V1");
+        assert_eq!(to_upper_camel_case("_"), "V");
+    }
+
+    #[test]
+    fn test_nested_module_name() {
+        assert_eq!(
+            nested_module_name("ComponentInterest"),
+            "component_interest"
+        );
+        assert_eq!(nested_module_name("HTTPRequest"), "http_request");
+        assert_eq!(nested_module_name("ABTestConfig"), "ab_test_config");
+        assert_eq!(nested_module_name("Vector3F"), "vector3_f");
+        assert_eq!(nested_module_name("My_Type"), "my_type");
+        assert_eq!(nested_module_name("query"), "query_");
+    }
+
+    #[test]
+    fn test_root_path() {
+        assert_eq!(root_path("generated").unwrap(), "crate::generated");
+        assert_eq!(
+            root_path("crate::net::schema").unwrap(),
+            "crate::net::schema"
+        );
+        assert_eq!(root_path("::my_schema").unwrap(), "::my_schema");
+        assert_eq!(root_path("crate").unwrap(), "crate");
+        assert_eq!(root_path("super::schema").unwrap(), "schema_root");
+        assert!(root_path("crate::").is_err());
+        assert!(root_alias("crate::generated", 2).unwrap().is_none());
+        assert_eq!(
+            root_alias("self", 2).unwrap().unwrap().to_string(),
+            quote! {
+                #[allow(unused_imports)]
+                use super::super as schema_root;
+            }
+            .to_string()
+        );
+        assert_eq!(
+            root_alias("super::schema", 1).unwrap().unwrap().to_string(),
+            quote! {
+                #[allow(unused_imports)]
+                use super::super::schema as schema_root;
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_escape_keywords() {
+        assert_eq!(rust_name("position"), "position");
+        assert_eq!(rust_name("match"), "r#match");
+        assert_eq!(rust_name("async"), "r#async");
+        assert_eq!(rust_name("self"), "self_");
+        assert_eq!(rust_name("Self"), "Self_");
+        assert_eq!(module_file_name("type"), "type");
+        assert_eq!(module_file_name("super"), "super_");
+        assert_eq!(nested_module_name("Type"), "r#type");
+
+        let member = Member {
+            name: "type".to_owned(),
+            m_type: DataType::Uint32,
+            id: 1,
+            comments: vec![],
+            annotations: vec![],
+        };
+        assert_eq!(
+            member.generate_one().unwrap().to_string(),
+            quote! {
+                #[field_name("type")]
+                #[field_id(1u32)]
+                #[spatial_type("uint32")]
+                r#type: u32
+            }
+            .to_string()
+        );
+        let variant = Variant {
+            name: "SELF".to_owned(),
+            id: 0,
+            comments: vec![],
+            annotations: vec![],
+        };
+        assert_eq!(
+            variant.generate_one().unwrap().to_string(),
+            quote! { #[value(0u32)] Self_ }.to_string()
+        );
+    }
+
+    #[test]
+    fn test_allow_non_camel_case_type_names() {
+        let ty = |name: &str| Type {
+            name: name.to_owned(),
+            members: vec![],
+            types: vec![],
+            enums: vec![],
+            comments: vec![],
+            annotations: vec![],
+        };
+        assert_eq!(
+            ty("type").generate_one().unwrap().to_string(),
+            quote! {
+                #[allow(dead_code)]
+                #[allow(non_camel_case_types)]
+                #[derive(SpatialType)]
+                pub struct r#type {}
+            }
+            .to_string()
+        );
+        assert_eq!(
+            ty("HTTPRequest").generate_one().unwrap().to_string(),
+            quote! {
+                #[allow(dead_code)]
+                #[derive(SpatialType)]
+                pub struct HTTPRequest {}
+            }
+            .to_string()
+        );
+        assert!(allow_non_camel_case("My_Type").is_some());
+        assert!(allow_non_camel_case("_Private").is_none());
+        assert_eq!(
+            event().get_types("farm").unwrap().to_string(),
+            quote! {
+                #[allow(non_camel_case_types)]
+                pub type farmRabbitBornEvent = u32;
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_annotation_rust_attribute() {
+        let annotation = |name: &str, values| Annotation {
+            name: vec![name.to_owned()],
+            values,
+        };
+        assert_eq!(
+            annotation(
+                "RustAttribute",
+                vec![AnnotationValue::String(
+                    "serde(rename = \\\"kind\\\")".to_owned()
+                )]
+            )
+            .rust_attribute()
+            .unwrap()
+            .map(|t| t.to_string()),
+            Some(quote! { #[serde(rename = "kind")] }.to_string())
+        );
+        assert!(annotation("Range", vec![])
+            .rust_attribute()
+            .unwrap()
+            .is_none());
+    }
 }
@@ -0,0 +1,422 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    ast::{
+        ASTBuilder, ASTNode, Annotation, AnnotationValue, Component, Enum, SchemaFile, Type, AST,
+    },
+    error::Error,
+    output::Formatting,
+};
+
+/// The codegen settings of a project, usually versioned next to its schemas in
+/// a `spatialos-codegen.toml`:
+///
+/// ```toml
+/// schemas = ["schema", "dependencies/schema"]
+/// output = "src/generated"
+/// module = "crate::generated"
+/// rustfmt = false
+/// packages = ["game", "improbable.restricted"]
+///
+/// [derives]
+/// types = ["Debug", "Clone"]
+/// components = ["Debug"]
+/// enums = ["PartialEq"]
+///
+/// [type-overrides]
+/// "improbable.Coordinates" = "crate::math::Coordinates"
+/// "EntityId" = "crate::worker::EntityId"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Directories searched for schema files.
+    pub schemas: Vec<PathBuf>,
+    /// Directory the code is generated in.
+    pub output: Option<PathBuf>,
+    /// Module path of the generated code, see `AST::generate`.
+    pub module: String,
+    /// Runs the generated code through the `rustfmt` binary.
+    pub rustfmt: bool,
+    /// Packages to generate code for, along with their subpackages and the
+    /// schemas they import, which their code refers to. Every package is
+    /// generated when empty.
+    pub packages: Vec<String>,
+    /// Extra traits derived by the generated items.
+    pub derives: Derives,
+    /// Rust types used instead of the generated ones, by qualified schema
+    /// name. `EntityId` and `Entity` replace the root types of the same name.
+    pub type_overrides: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Derives {
+    pub types: Vec<String>,
+    pub components: Vec<String>,
+    pub enums: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            schemas: Vec::new(),
+            output: None,
+            module: "generated".to_owned(),
+            rustfmt: false,
+            packages: Vec::new(),
+            derives: Derives::default(),
+            type_overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|e| Error::Config {
+            message: e.to_string(),
+        })
+    }
+}
+
+fn derive(derives: &[String]) -> Option<Annotation> {
+    if derives.is_empty() {
+        None
+    } else {
+        Some(Annotation {
+            name: vec!["RustAttribute".to_owned()],
+            values: vec![AnnotationValue::String(format!(
+                "derive({})",
+                derives.join(", ")
+            ))],
+        })
+    }
+}
+
+/// Whether `package` is the package `filter` or one of its subpackages.
+fn includes(filter: &str, package: &str) -> bool {
+    package == filter || package.starts_with(&format!("{}.", filter))
+}
+
+impl Config {
+    /// The name of the configuration file.
+    pub const FILE_NAME: &'static str = "spatialos-codegen.toml";
+
+    /// Reads the configuration at `path`. Relative `schemas` and `output`
+    /// directories are relative to the directory of the file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(Error::io(path))?;
+        let mut config = contents.parse::<Self>().map_err(|e| Error::Config {
+            message: format!("{}: {}", path.display(), e),
+        })?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        config.schemas = config.schemas.iter().map(|dir| base.join(dir)).collect();
+        config.output = config.output.map(|output| base.join(output));
+        Ok(config)
+    }
+
+    /// Parses the schemas under the `schemas` directories.
+    pub fn build(&self) -> Result<AST, Error> {
+        if self.schemas.is_empty() {
+            return Err(Error::Config {
+                message: "no schema directory, set `schemas`".to_owned(),
+            });
+        }
+        self.schemas
+            .iter()
+            .fold(ASTBuilder::default(), |builder, dir| {
+                builder.with_directory(dir)
+            })
+            .build()
+    }
+
+    /// Generates the code of the schemas in the `output` directory.
+    pub fn generate(&self) -> Result<(), Error> {
+        self.build()?.write(self)
+    }
+
+    /// Parses, validates and resolves the schemas without generating anything.
+    pub fn check(&self) -> Result<(), Error> {
+        self.build()?.resolve(self).map(|_| ())
+    }
+
+    pub(crate) fn output(&self) -> Result<&Path, Error> {
+        self.output.as_deref().ok_or_else(|| Error::Config {
+            message: "no output directory, set `output`".to_owned(),
+        })
+    }
+
+    pub(crate) fn formatting(&self) -> Formatting {
+        if self.rustfmt {
+            Formatting::Rustfmt
+        } else {
+            Formatting::Pretty
+        }
+    }
+
+    fn is_included(&self, package: &str) -> bool {
+        self.packages.is_empty() || self.packages.iter().any(|p| includes(p, package))
+    }
+
+    /// The paths of the schemas of the included packages and of every schema
+    /// they import, directly or not.
+    fn included(&self, schemas: &[&SchemaFile]) -> BTreeSet<String> {
+        let imports = schemas
+            .iter()
+            .map(|schema| (schema.path.as_str(), &schema.imports))
+            .collect::<BTreeMap<_, _>>();
+        let mut pending = schemas
+            .iter()
+            .filter(|schema| self.is_included(&schema.package_name.join(".")))
+            .map(|schema| schema.path.clone())
+            .collect::<Vec<_>>();
+        let mut included = BTreeSet::new();
+        while let Some(path) = pending.pop() {
+            if let Some(imports) = imports.get(path.as_str()) {
+                if included.insert(path) {
+                    pending.extend(imports.iter().cloned());
+                }
+            }
+        }
+        included
+    }
+
+    fn derive_enum(&self, mut en: Enum) -> Enum {
+        en.annotations.extend(derive(&self.derives.enums));
+        en
+    }
+
+    fn derive_nested(&self, types: Vec<Type>, enums: Vec<Enum>) -> (Vec<Type>, Vec<Enum>) {
+        (
+            types.into_iter().map(|ty| self.derive_type(ty)).collect(),
+            enums.into_iter().map(|en| self.derive_enum(en)).collect(),
+        )
+    }
+
+    fn derive_type(&self, mut ty: Type) -> Type {
+        ty.annotations.extend(derive(&self.derives.types));
+        let (types, enums) = self.derive_nested(ty.types, ty.enums);
+        ty.types = types;
+        ty.enums = enums;
+        ty
+    }
+
+    fn derive_component(&self, mut comp: Component) -> Component {
+        comp.annotations.extend(derive(&self.derives.components));
+        let (types, enums) = self.derive_nested(comp.types, comp.enums);
+        comp.types = types;
+        comp.enums = enums;
+        comp
+    }
+
+    fn derive_schema(&self, mut schema: SchemaFile) -> SchemaFile {
+        let (types, enums) = self.derive_nested(schema.types, schema.enums);
+        schema.types = types;
+        schema.enums = enums;
+        schema.components = schema
+            .components
+            .into_iter()
+            .map(|comp| self.derive_component(comp))
+            .collect();
+        schema
+    }
+
+    fn apply_node(&self, node: ASTNode, included: &BTreeSet<String>) -> Option<ASTNode> {
+        match node {
+            ASTNode::PackageNode(mut package) => {
+                package.inner = package
+                    .inner
+                    .into_iter()
+                    .filter_map(|n| self.apply_node(n, included))
+                    .collect();
+                if package.inner.is_empty() {
+                    None
+                } else {
+                    Some(ASTNode::PackageNode(package))
+                }
+            }
+            ASTNode::SchemaNode(schema) => Some(schema)
+                .filter(|schema| included.contains(&schema.path))
+                .map(|schema| ASTNode::SchemaNode(self.derive_schema(schema))),
+        }
+    }
+
+    /// Keeps the included packages of a resolved AST, with the schemas they
+    /// import, and adds the derives to their items.
+    pub(crate) fn apply(&self, mut ast: AST) -> Result<AST, Error> {
+        let mut found = Vec::new();
+        ast.inner.iter().for_each(|node| node.schemas(&mut found));
+        if let Some(package) = self.packages.iter().find(|p| {
+            !found
+                .iter()
+                .any(|schema| includes(p, &schema.package_name.join(".")))
+        }) {
+            return Err(Error::Config {
+                message: format!("package `{}` doesn't match any schema", package),
+            });
+        }
+        let included = self.included(&found);
+        ast.inner = ast
+            .inner
+            .into_iter()
+            .filter_map(|node| self.apply_node(node, &included))
+            .collect();
+        Ok(ast)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::schema_file::parse_schema;
+
+    fn schema(path: &str, source: &str) -> ASTNode {
+        let schema = parse_schema(path, source.as_bytes())
+            .unwrap()
+            .with_name(path.rsplit('/').next().unwrap().to_owned())
+            .with_path(path.to_owned() + ".schema")
+            .build()
+            .unwrap();
+        ASTNode::package_schema(&schema, &schema.package_name)
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = "schemas = [\"schema\"]
+            module = \"crate::schema\"
+            packages = [\"game\"]
+
+            [derives]
+            types = [\"Debug\", \"Clone\"]
+
+            [type-overrides]
+            \"improbable.Coordinates\" = \"crate::math::Coordinates\""
+            .parse::<Config>()
+            .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                schemas: vec![PathBuf::from("schema")],
+                module: "crate::schema".to_owned(),
+                packages: vec!["game".to_owned()],
+                derives: Derives {
+                    types: vec!["Debug".to_owned(), "Clone".to_owned()],
+                    ..Derives::default()
+                },
+                type_overrides: Some((
+                    "improbable.Coordinates".to_owned(),
+                    "crate::math::Coordinates".to_owned()
+                ))
+                .into_iter()
+                .collect(),
+                ..Config::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_reject_unknown_keys() {
+        let error = |config: &str| config.parse::<Config>().unwrap_err().to_string();
+        assert!(
+            error("schema = [\"schema\"]").starts_with("unknown field `schema`, expected one of")
+        );
+        assert!(error("[derives]\nstructs = [\"Debug\"]").starts_with(
+            "unknown field `structs`, expected one of `types`, `components`, `enums`"
+        ));
+    }
+
+    #[test]
+    fn test_apply_packages_and_derives() {
+        let ast = || AST {
+            inner: vec![
+                schema(
+                    "game",
+                    "package game; type A { type B {} } enum C { X = 0; }",
+                ),
+                schema("other", "package other; type D {}"),
+            ],
+        };
+        let config = |packages: &[&str]| Config {
+            packages: packages.iter().map(|p| p.to_string()).collect(),
+            derives: Derives {
+                types: vec!["Debug".to_owned()],
+                enums: vec!["PartialEq".to_owned(), "Eq".to_owned()],
+                ..Derives::default()
+            },
+            ..Config::default()
+        };
+        let derive = |derive: &str| {
+            vec![Annotation {
+                name: vec!["RustAttribute".to_owned()],
+                values: vec![AnnotationValue::String(derive.to_owned())],
+            }]
+        };
+
+        let applied = config(&["game"]).apply(ast()).unwrap();
+        match applied.inner.as_slice() {
+            [ASTNode::PackageNode(package)] => match package.inner.as_slice() {
+                [ASTNode::SchemaNode(schema)] => {
+                    assert_eq!(schema.types[0].annotations, derive("derive(Debug)"));
+                    assert_eq!(
+                        schema.types[0].types[0].annotations,
+                        derive("derive(Debug)")
+                    );
+                    assert_eq!(schema.enums[0].annotations, derive("derive(PartialEq, Eq)"));
+                }
+                nodes => panic!("expected the game schema, found {:?}", nodes),
+            },
+            nodes => panic!("expected the game package, found {:?}", nodes),
+        }
+        assert_eq!(
+            config(&["gam"])
+                .apply(AST::default())
+                .unwrap_err()
+                .to_string(),
+            "package `gam` doesn't match any schema"
+        );
+    }
+
+    #[test]
+    fn test_apply_keeps_imported_schemas() {
+        let ast = AST {
+            inner: vec![
+                schema("base/vector", "package base; type Vector {}"),
+                schema(
+                    "base/transform",
+                    "package base; import \"base/vector.schema\"; type Transform {}",
+                ),
+                schema("base/unused", "package base; type Unused {}"),
+                schema(
+                    "game/player",
+                    "package game; import \"base/transform.schema\"; type Player {}",
+                ),
+                schema("other/thing", "package other; type Thing {}"),
+            ],
+        };
+        let config = Config {
+            packages: vec!["game".to_owned()],
+            ..Config::default()
+        };
+        let applied = config.apply(ast).unwrap();
+        let mut kept = Vec::new();
+        applied.inner.iter().for_each(|n| n.schemas(&mut kept));
+        assert_eq!(
+            kept.iter().map(|s| s.path.as_str()).collect::<Vec<_>>(),
+            vec![
+                "base/vector.schema",
+                "base/transform.schema",
+                "game/player.schema"
+            ]
+        );
+    }
+}
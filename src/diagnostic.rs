@@ -0,0 +1,224 @@
+use std::fmt::{self, Display, Formatter};
+
+use nom::{error::VerboseError, Err};
+
+use crate::parser::{
+    recovery::{describe, recovering},
+    IResult,
+};
+
+/// A syntax error located in a schema file, rendered with the offending line
+/// and a caret under the column where parsing failed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    /// 1-based line of the failure.
+    pub line: usize,
+    /// 1-based column of the failure, counted in characters.
+    pub column: usize,
+    pub message: String,
+    /// The whole source line containing the failure.
+    pub snippet: String,
+}
+
+impl Diagnostic {
+    /// Locates `remaining`, a suffix of `source`, and describes it with `message`.
+    pub fn new<S: AsRef<str>, M: ToString>(
+        file: S,
+        source: &[u8],
+        remaining: &[u8],
+        message: M,
+    ) -> Self {
+        let offset = source.len() - remaining.len().min(source.len());
+        let line_start = source[..offset]
+            .iter()
+            .rposition(|&c| c == b'\n')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let line_end = source[offset..]
+            .iter()
+            .position(|&c| c == b'\n')
+            .map(|i| offset + i)
+            .unwrap_or_else(|| source.len());
+        let snippet = String::from_utf8_lossy(&source[line_start..line_end]);
+        let prefix = String::from_utf8_lossy(&source[line_start..offset]);
+        Self {
+            file: file.as_ref().to_string(),
+            line: source[..offset].iter().filter(|&&c| c == b'\n').count() + 1,
+            column: prefix.chars().count() + 1,
+            message: message.to_string(),
+            snippet: snippet.trim_end_matches('\r').to_string(),
+        }
+    }
+
+    /// Builds a diagnostic from the innermost context of a parser failure,
+    /// pointing at where that context started.
+    pub(crate) fn from_parse_error<S: AsRef<str>>(
+        file: S,
+        source: &[u8],
+        error: Err<VerboseError<&[u8]>>,
+    ) -> Self {
+        match error {
+            Err::Error(e) | Err::Failure(e) => {
+                let (remaining, message) =
+                    describe(&e).unwrap_or_else(|| (source, "invalid syntax".to_string()));
+                Self::new(file, source, remaining, message)
+            }
+            Err::Incomplete(_) => Self::new(file, source, &[], "unexpected end of file"),
+        }
+    }
+
+    /// Runs `parser` over the whole `source`, returning a diagnostic for every
+    /// error it recovered from as well as for a final failure, in source order.
+    pub(crate) fn run<'a, S, T, F>(file: S, source: &'a [u8], parser: F) -> Result<T, Vec<Self>>
+    where
+        S: AsRef<str>,
+        F: FnOnce(&'a [u8]) -> IResult<&'a [u8], T>,
+    {
+        let (result, recovered) = recovering(|| parser(source));
+        let mut diagnostics = recovered
+            .into_iter()
+            .map(|(remaining, message)| {
+                let remaining = &source[source.len() - remaining..];
+                Self::new(&file, source, remaining, message)
+            })
+            .collect::<Vec<_>>();
+        match result {
+            Ok((_, output)) if diagnostics.is_empty() => return Ok(output),
+            Ok(_) => {}
+            Err(error) => diagnostics.push(Self::from_parse_error(&file, source, error)),
+        }
+        diagnostics.sort_by_key(|d| (d.line, d.column));
+        Err(diagnostics)
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        let indent = self
+            .snippet
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        writeln!(f, "error: {}", self.message)?;
+        writeln!(
+            f,
+            "{}--> {}:{}:{}",
+            gutter, self.file, self.line, self.column
+        )?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.snippet)?;
+        write!(f, "{} | {}^", gutter, indent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::schema_file::parse_schema;
+
+    fn diagnostics(source: &str) -> Vec<Diagnostic> {
+        parse_schema("game/health.schema", source.as_bytes())
+            .map(|_| ())
+            .unwrap_err()
+    }
+
+    fn diagnostic(source: &str) -> Diagnostic {
+        let mut diagnostics = diagnostics(source);
+        assert_eq!(diagnostics.len(), 1);
+        diagnostics.remove(0)
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let diagnostic =
+            diagnostic("package game;\n\ncomponent Health {\n    id = 1;\n    uint32 hp = 1\n}\n");
+        assert_eq!(
+            (
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.message.as_str()
+            ),
+            (5, 18, "expected `;` after field ID")
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: expected `;` after field ID\n \
+             --> game/health.schema:5:18\n  \
+             |\n\
+             5 |     uint32 hp = 1\n  \
+             |                  ^"
+        );
+    }
+
+    #[test]
+    fn test_unexpected_top_level() {
+        let diagnostic = diagnostic("package game;\ntype Empty {}\nstruct Foo {}\n");
+        assert_eq!(
+            (
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.message.as_str()
+            ),
+            (3, 1, "expected `type`, `component` or `enum`")
+        );
+    }
+
+    #[test]
+    fn test_every_error_in_source_order() {
+        let diagnostics =
+            diagnostics("package game;\ntype A { uint32 a 1; }\nstruct B;\nenum C { D 0; }\n");
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.line, d.column, d.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, 18, "expected `=` after field name"),
+                (3, 1, "expected `type`, `component` or `enum`"),
+                (4, 11, "expected `=` after enum value name"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8_in_comment() {
+        let source = b"package game;\n/* \xff\xfe\xfd \xff\xfe\xfd \xff\xfe\xfd */\ntype A { uint32 a 1; }\n";
+        let diagnostics = parse_schema("game/a.schema", source)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.line, d.column, d.snippet.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(3, 18, "type A { uint32 a 1; }")]
+        );
+        let diagnostic = Diagnostic::new("game/a.schema", source, &source[17..], "here");
+        assert_eq!(
+            (diagnostic.line, diagnostic.column, diagnostic.snippet),
+            (
+                2,
+                4,
+                "/* \u{fffd}\u{fffd}\u{fffd} \u{fffd}\u{fffd}\u{fffd} \u{fffd}\u{fffd}\u{fffd} */"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn test_missing_package() {
+        let diagnostic = diagnostic("type Empty {}");
+        assert_eq!(
+            (
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.message.as_str()
+            ),
+            (1, 1, "expected `package` declaration")
+        );
+    }
+}
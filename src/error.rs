@@ -0,0 +1,208 @@
+use crate::diagnostic::Diagnostic;
+
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing `path` failed.
+    Io { path: PathBuf, source: io::Error },
+    /// A schema file could not be parsed.
+    Parse(Diagnostic),
+    /// A schema file does not declare its package.
+    MissingPackage { file: String },
+    /// An `import` points at a file that isn't under any schema directory.
+    MissingImport { file: String, import: String },
+    /// A type reference doesn't match any visible declaration.
+    UnresolvedType {
+        file: String,
+        declaration: String,
+        name: String,
+    },
+    /// A type reference matches several declarations in the same scope.
+    AmbiguousType {
+        file: String,
+        declaration: String,
+        name: String,
+        candidates: Vec<String>,
+    },
+    /// A type reference resolves to something that can't be used there.
+    InvalidReference {
+        file: String,
+        declaration: String,
+        message: String,
+    },
+    /// Two declarations use the same ID or name where it must be unique.
+    Duplicate {
+        file: String,
+        declaration: String,
+        message: String,
+        previous_file: String,
+        previous_declaration: String,
+    },
+    /// The module path the code is generated at isn't a valid Rust path.
+    InvalidModulePath { module: String, message: String },
+    /// A generated file could not be formatted.
+    Format { path: PathBuf, message: String },
+    /// The configuration file is invalid or doesn't match the schemas.
+    Config { message: String },
+    /// Several independent failures, reported together.
+    Multiple(Vec<Error>),
+    /// Rust code could not be generated for a declaration.
+    Codegen {
+        file: String,
+        declaration: String,
+        message: String,
+    },
+}
+
+impl Error {
+    pub(crate) fn io<P: AsRef<Path>>(path: P) -> impl FnOnce(io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        move |source| Self::Io { path, source }
+    }
+
+    /// Reports several failures at once, flattening nested lists.
+    pub(crate) fn multiple(errors: Vec<Error>) -> Self {
+        let mut errors = errors
+            .into_iter()
+            .flat_map(|e| match e {
+                Self::Multiple(errors) => errors,
+                e => vec![e],
+            })
+            .collect::<Vec<_>>();
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            Self::Multiple(errors)
+        }
+    }
+
+    pub(crate) fn codegen<S: AsRef<str>, M: ToString>(declaration: S, message: M) -> Self {
+        Self::Codegen {
+            file: String::new(),
+            declaration: declaration.as_ref().to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Prefixes the declaration of a codegen error with its enclosing item.
+    pub(crate) fn within<S: AsRef<str>>(self, name: S) -> Self {
+        match self {
+            Self::Codegen {
+                file,
+                declaration,
+                message,
+            } => Self::Codegen {
+                file,
+                declaration: if declaration.is_empty() {
+                    name.as_ref().to_string()
+                } else {
+                    name.as_ref().to_string() + "." + &declaration
+                },
+                message,
+            },
+            error => error,
+        }
+    }
+
+    pub(crate) fn in_file<S: AsRef<str>>(self, path: S) -> Self {
+        match self {
+            Self::Codegen {
+                declaration,
+                message,
+                ..
+            } => Self::Codegen {
+                file: path.as_ref().to_string(),
+                declaration,
+                message,
+            },
+            error => error,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Self::Parse(diagnostic) => write!(f, "{}", diagnostic),
+            Self::Format { path, message } => write!(f, "{}: {}", path.display(), message),
+            Self::Config { message } => write!(f, "{}", message),
+            Self::Multiple(errors) => write!(
+                f,
+                "{}",
+                errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            ),
+            Self::InvalidModulePath { module, message } => {
+                write!(f, "invalid module path `{}`: {}", module, message)
+            }
+            Self::MissingPackage { file } => write!(f, "{}: missing package declaration", file),
+            Self::MissingImport { file, import } => write!(
+                f,
+                "{}: unable to find import \"{}\" under any schema directory",
+                file, import
+            ),
+            Self::UnresolvedType {
+                file,
+                declaration,
+                name,
+            } => write!(f, "{}: {}: unable to resolve {}", file, declaration, name),
+            Self::AmbiguousType {
+                file,
+                declaration,
+                name,
+                candidates,
+            } => write!(
+                f,
+                "{}: {}: ambiguous reference to {}, could be {}",
+                file,
+                declaration,
+                name,
+                candidates.join(", ")
+            ),
+            Self::Duplicate {
+                file,
+                declaration,
+                message,
+                previous_file,
+                previous_declaration,
+            } => write!(
+                f,
+                "{}: {}: {} (first declared as {} in {})",
+                file, declaration, message, previous_declaration, previous_file
+            ),
+            Self::InvalidReference {
+                file,
+                declaration,
+                message,
+            } => write!(f, "{}: {}: {}", file, declaration, message),
+            Self::Codegen {
+                file,
+                declaration,
+                message,
+            } => {
+                for prefix in [file, declaration].iter().filter(|p| !p.is_empty()) {
+                    write!(f, "{}: ", prefix)?;
+                }
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -1,10 +1,20 @@
 mod ast;
 mod codegen;
+mod config;
+mod diagnostic;
+mod error;
+mod output;
 mod parser;
 mod resolver;
+mod validator;
 
 #[macro_use]
 extern crate quote;
 
-pub use ast::ASTBuilder;
+pub use ast::{ASTBuilder, AST};
+pub use config::{Config, Derives};
+pub use diagnostic::Diagnostic;
+pub use error::Error;
+pub use output::Formatting;
 pub use resolver::resolve_types;
+pub use validator::validate;
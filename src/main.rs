@@ -0,0 +1,268 @@
+use std::{
+    ffi::OsString,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use spatialos_codegen::{Config, Error};
+
+const USAGE: &str = "\
+Usage: spatialos-codegen <command> [options] [<schema-dir>...]
+
+Commands:
+    generate    Generate the Rust code of the schemas
+    check       Parse, resolve and validate the schemas without generating anything
+    dump-ast    Print the parsed schemas
+
+Options:
+    -c, --config <file>    Configuration file [default: spatialos-codegen.toml, if present]
+    -o, --out <dir>        Directory the code is generated in
+    -m, --module <path>    Rust module path of the generated code [default: generated]
+        --rustfmt          Run the generated code through rustfmt
+    -h, --help             Print this help
+
+The schema directories and options given on the command line replace the ones
+of the configuration file.
+
+Exit status:
+    0    Success
+    1    The schemas are invalid
+    2    The command line or the configuration is invalid
+    3    Reading, writing or formatting a file failed
+";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Generate,
+    Check,
+    DumpAst,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    command: Command,
+    config: Option<PathBuf>,
+    out: Option<PathBuf>,
+    module: Option<String>,
+    rustfmt: bool,
+    schemas: Vec<PathBuf>,
+}
+
+/// Parses the command line, returning `None` when help was asked for.
+fn parse_args<I: IntoIterator<Item = OsString>>(args: I) -> Result<Option<Args>, lexopt::Error> {
+    use lexopt::prelude::*;
+
+    let mut parser = lexopt::Parser::from_args(args);
+    let mut command = None;
+    let mut config = None;
+    let mut out = None;
+    let mut module = None;
+    let mut rustfmt = false;
+    let mut schemas = Vec::new();
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short('h') | Long("help") => return Ok(None),
+            Short('c') | Long("config") => config = Some(PathBuf::from(parser.value()?)),
+            Short('o') | Long("out") => out = Some(PathBuf::from(parser.value()?)),
+            Short('m') | Long("module") => module = Some(parser.value()?.string()?),
+            Long("rustfmt") => rustfmt = true,
+            Value(value) if command.is_none() => command = Some(value.string()?),
+            Value(value) => schemas.push(PathBuf::from(value)),
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let command = match command.as_deref() {
+        Some("generate") => Command::Generate,
+        Some("check") => Command::Check,
+        Some("dump-ast") => Command::DumpAst,
+        Some(command) => return Err(format!("unknown command `{}`", command).into()),
+        None => return Err("missing command".into()),
+    };
+    if out.is_some() && command != Command::Generate {
+        return Err("--out is only used by `generate`".into());
+    }
+    Ok(Some(Args {
+        command,
+        config,
+        out,
+        module,
+        rustfmt,
+        schemas,
+    }))
+}
+
+impl Args {
+    /// The configuration file, with the settings of the command line applied.
+    fn config(&self) -> Result<Config, Error> {
+        let mut config = match &self.config {
+            Some(path) => Config::from_file(path)?,
+            None if Path::new(Config::FILE_NAME).exists() => Config::from_file(Config::FILE_NAME)?,
+            None => Config::default(),
+        };
+        if !self.schemas.is_empty() {
+            config.schemas = self.schemas.clone();
+        }
+        if let Some(out) = &self.out {
+            config.output = Some(out.clone());
+        }
+        if let Some(module) = &self.module {
+            config.module = module.clone();
+        }
+        config.rustfmt |= self.rustfmt;
+        Ok(config)
+    }
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    let config = args.config()?;
+    match args.command {
+        Command::Generate => config.generate(),
+        Command::Check => config.check(),
+        Command::DumpAst => match writeln!(io::stdout(), "{:#?}", config.build()?) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(Error::Io {
+                path: PathBuf::from("<stdout>"),
+                source: e,
+            }),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// The exit status of a failed run, as documented in `USAGE`.
+fn exit_code(error: &Error) -> i32 {
+    match error {
+        Error::InvalidModulePath { .. } | Error::Config { .. } => 2,
+        Error::Io { .. } | Error::Format { .. } => 3,
+        Error::Multiple(errors) => errors.iter().map(exit_code).max().unwrap_or(1),
+        _ => 1,
+    }
+}
+
+fn main() {
+    let args = match parse_args(std::env::args_os().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = run(args) {
+        eprintln!("{}", e);
+        process::exit(exit_code(&e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse(args: &str) -> Result<Option<Args>, String> {
+        parse_args(args.split_whitespace().map(OsString::from)).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse("generate --rustfmt -o src/schema -m crate::schema schema deps/schema"),
+            Ok(Some(Args {
+                command: Command::Generate,
+                config: None,
+                out: Some(PathBuf::from("src/schema")),
+                module: Some("crate::schema".to_owned()),
+                rustfmt: true,
+                schemas: vec![PathBuf::from("schema"), PathBuf::from("deps/schema")],
+            }))
+        );
+        assert_eq!(
+            parse("check -c codegen.toml"),
+            Ok(Some(Args {
+                command: Command::Check,
+                config: Some(PathBuf::from("codegen.toml")),
+                out: None,
+                module: None,
+                rustfmt: false,
+                schemas: vec![],
+            }))
+        );
+        assert_eq!(parse("generate --help"), Ok(None));
+        assert_eq!(
+            parse("dump-ast --out src schema"),
+            Err("--out is only used by `generate`".to_owned())
+        );
+        assert_eq!(
+            parse("build schema"),
+            Err("unknown command `build`".to_owned())
+        );
+        assert_eq!(
+            parse("build --out src schema"),
+            Err("unknown command `build`".to_owned())
+        );
+        assert_eq!(parse(""), Err("missing command".to_owned()));
+        assert_eq!(parse("--out src"), Err("missing command".to_owned()));
+    }
+
+    #[test]
+    fn test_command_line_overrides_config() {
+        let path = std::env::temp_dir().join(format!("spatialos-codegen-{}.toml", process::id()));
+        std::fs::write(
+            &path,
+            "schemas = [\"schema\"]\nmodule = \"crate::schema\"\n",
+        )
+        .unwrap();
+        let args = |schemas: Vec<PathBuf>| Args {
+            command: Command::Generate,
+            config: Some(path.clone()),
+            out: Some(PathBuf::from("out")),
+            module: None,
+            rustfmt: true,
+            schemas,
+        };
+        let (from_file, overridden) = (
+            args(vec![]).config().unwrap(),
+            args(vec![PathBuf::from("other")]).config().unwrap(),
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            (
+                &from_file.schemas,
+                &from_file.output,
+                from_file.module.as_str(),
+                from_file.rustfmt
+            ),
+            (
+                &vec![std::env::temp_dir().join("schema")],
+                &Some(PathBuf::from("out")),
+                "crate::schema",
+                true
+            )
+        );
+        assert_eq!(overridden.schemas, vec![PathBuf::from("other")]);
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let io = || Error::Io {
+            path: PathBuf::from("schema"),
+            source: std::io::ErrorKind::NotFound.into(),
+        };
+        let missing = || Error::MissingPackage {
+            file: "a.schema".to_owned(),
+        };
+        assert_eq!(exit_code(&missing()), 1);
+        assert_eq!(
+            exit_code(&Error::Config {
+                message: "no schema directory, set `schemas`".to_owned()
+            }),
+            2
+        );
+        assert_eq!(exit_code(&io()), 3);
+        assert_eq!(exit_code(&Error::Multiple(vec![missing(), io()])), 3);
+    }
+}
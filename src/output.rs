@@ -0,0 +1,187 @@
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use quote::__private::TokenStream;
+use walkdir::WalkDir;
+
+use crate::error::Error;
+
+/// First line of every generated file, which tells them apart from files
+/// written by hand when cleaning up the output directory.
+pub(crate) const HEADER: &str = "// @generated by spatialos-codegen, do not edit.\n";
+
+/// The path and contents of a generated file.
+pub(crate) type OutputFile = (PathBuf, String);
+
+/// How the generated code is formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Formatting {
+    /// Pretty-printed by the library itself.
+    #[default]
+    Pretty,
+    /// Pretty-printed, then run through the `rustfmt` binary, failing when it
+    /// isn't installed or rejects the code.
+    Rustfmt,
+}
+
+fn rustfmt(code: String) -> Result<String, String> {
+    let mut child = Command::new("rustfmt")
+        .args(["--edition", "2018"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("unable to run rustfmt: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(code.as_bytes())
+            .map_err(|e| format!("unable to run rustfmt: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("unable to run rustfmt: {}", e))?;
+    if output.status.success() {
+        String::from_utf8(output.stdout).map_err(|e| format!("rustfmt output: {}", e))
+    } else {
+        Err(format!(
+            "rustfmt failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Formats generated code, returning why it couldn't be formatted otherwise.
+pub(crate) fn format_code(code: TokenStream, formatting: Formatting) -> Result<String, String> {
+    let file = syn::parse2::<syn::File>(code)
+        .map_err(|e| format!("the generated code isn't valid Rust: {}", e))?;
+    let pretty = prettyplease::unparse(&file);
+    match formatting {
+        Formatting::Pretty => Ok(pretty),
+        Formatting::Rustfmt => rustfmt(pretty),
+    }
+}
+
+fn is_generated(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .map(|contents| contents.starts_with(HEADER))
+        .unwrap_or(false)
+}
+
+/// Writes the files whose contents changed, leaving the others untouched so
+/// Cargo doesn't rebuild them. Generated files under `root` that aren't part
+/// of `files` anymore are removed, along with the directories they leave
+/// empty. Anything else under `root` is kept.
+pub(crate) fn write_files<P: AsRef<Path>>(root: P, files: Vec<OutputFile>) -> Result<(), Error> {
+    let root = root.as_ref();
+    for (path, contents) in &files {
+        if std::fs::read(path).ok().as_deref() == Some(contents.as_bytes()) {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(Error::io(parent))?;
+        }
+        std::fs::write(path, contents).map_err(Error::io(path))?;
+    }
+
+    let written = files
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect::<HashSet<_>>();
+    let stale = WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension() == Some(OsStr::new("rs")))
+        .filter(|path| !written.contains(path) && is_generated(path))
+        .collect::<Vec<_>>();
+    for path in stale {
+        std::fs::remove_file(&path).map_err(Error::io(&path))?;
+        for directory in path.ancestors().skip(1) {
+            if !directory.starts_with(root) || directory == root {
+                break;
+            }
+            if std::fs::remove_dir(directory).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_format_code() {
+        assert_eq!(
+            format_code(quote! { pub struct A { b: u32 } }, Formatting::Pretty),
+            Ok("pub struct A {\n    b: u32,\n}\n".to_owned())
+        );
+        assert!(format_code(quote! { pub struct }, Formatting::Pretty).is_err());
+    }
+
+    #[test]
+    fn test_write_only_changed_files() {
+        let root =
+            std::env::temp_dir().join(format!("spatialos-codegen-output-{}", std::process::id()));
+        let file = |path: &str, contents: &str| (root.join(path), HEADER.to_owned() + contents);
+        std::fs::create_dir_all(root.join("game")).unwrap();
+        std::fs::write(root.join("game/notes.rs"), "// Written by hand").unwrap();
+        write_files(
+            &root,
+            vec![
+                file("mod.rs", "pub mod game;"),
+                file("game/mod.rs", "pub mod a;"),
+                file("game/a.rs", "struct A;"),
+                file("old/mod.rs", "pub mod b;"),
+                file("old/b.rs", "struct B;"),
+            ],
+        )
+        .unwrap();
+        let modified = |path: &str| {
+            std::fs::metadata(root.join(path))
+                .and_then(|m| m.modified())
+                .unwrap()
+        };
+        let unchanged = modified("game/a.rs");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        write_files(
+            &root,
+            vec![
+                file("mod.rs", "pub mod game;"),
+                file("game/mod.rs", "pub mod a;\npub mod c;"),
+                file("game/a.rs", "struct A;"),
+                file("game/c.rs", "struct C;"),
+            ],
+        )
+        .unwrap();
+        let exists = |path: &str| root.join(path).exists();
+        let result = (
+            modified("game/a.rs") == unchanged,
+            std::fs::read_to_string(root.join("game/mod.rs")).unwrap(),
+            exists("game/c.rs"),
+            exists("game/notes.rs"),
+            exists("old"),
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(
+            result,
+            (
+                true,
+                HEADER.to_owned() + "pub mod a;\npub mod c;",
+                true,
+                true,
+                false
+            )
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    ast::{Annotation, AnnotationValue},
+    parser::utils::{identifier, parse_comment, ws0},
+};
+
+use nom::{
+    branch::alt,
+    bytes::complete::{escaped, is_not, tag},
+    character::complete::{char, digit1, one_of},
+    combinator::{map, map_res, opt, recognize, value},
+    multi::{fold_many0, separated_list0, separated_list1},
+    sequence::{delimited, pair, separated_pair, tuple},
+};
+
+use crate::parser::IResult;
+
+fn parse_bool(input: &[u8]) -> IResult<&[u8], AnnotationValue> {
+    alt((
+        value(AnnotationValue::Bool(true), tag("true")),
+        value(AnnotationValue::Bool(false), tag("false")),
+    ))(input)
+}
+
+fn parse_number(input: &[u8]) -> IResult<&[u8], AnnotationValue> {
+    map(
+        map_res(
+            recognize(tuple((
+                opt(char('-')),
+                digit1,
+                opt(pair(char('.'), digit1)),
+                opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+            ))),
+            std::str::from_utf8,
+        ),
+        |n| AnnotationValue::Number(n.to_string()),
+    )(input)
+}
+
+pub fn parse_string_literal(input: &[u8]) -> IResult<&[u8], String> {
+    map(
+        map_res(
+            delimited(
+                char('"'),
+                opt(escaped(is_not("\\\""), '\\', one_of("\\\"nrt"))),
+                char('"'),
+            ),
+            |s: Option<&[u8]>| std::str::from_utf8(s.unwrap_or_default()),
+        ),
+        |s| s.to_string(),
+    )(input)
+}
+
+fn parse_list(input: &[u8]) -> IResult<&[u8], AnnotationValue> {
+    map(
+        delimited(
+            char('['),
+            ws0(separated_list0(ws0(char(',')), parse_value)),
+            char(']'),
+        ),
+        AnnotationValue::List,
+    )(input)
+}
+
+fn parse_map(input: &[u8]) -> IResult<&[u8], AnnotationValue> {
+    map(
+        delimited(
+            char('{'),
+            ws0(separated_list0(
+                ws0(char(',')),
+                separated_pair(parse_value, ws0(char(':')), parse_value),
+            )),
+            char('}'),
+        ),
+        AnnotationValue::Map,
+    )(input)
+}
+
+fn parse_path(input: &[u8]) -> IResult<&[u8], Vec<String>> {
+    separated_list1(char('.'), identifier)(input)
+}
+
+fn parse_arguments(input: &[u8]) -> IResult<&[u8], Vec<AnnotationValue>> {
+    delimited(
+        char('('),
+        ws0(separated_list0(ws0(char(',')), parse_value)),
+        char(')'),
+    )(input)
+}
+
+fn parse_instance(input: &[u8]) -> IResult<&[u8], AnnotationValue> {
+    map(
+        pair(parse_path, opt(ws0(parse_arguments))),
+        |(name, values)| match values {
+            Some(values) => AnnotationValue::Instance(Annotation { name, values }),
+            None => AnnotationValue::Reference(name),
+        },
+    )(input)
+}
+
+pub fn parse_value(input: &[u8]) -> IResult<&[u8], AnnotationValue> {
+    alt((
+        parse_bool,
+        parse_number,
+        map(parse_string_literal, AnnotationValue::String),
+        parse_list,
+        parse_map,
+        parse_instance,
+    ))(input)
+}
+
+pub fn parse_annotation(input: &[u8]) -> IResult<&[u8], Annotation> {
+    map(
+        delimited(
+            char('['),
+            ws0(pair(parse_path, opt(ws0(parse_arguments)))),
+            char(']'),
+        ),
+        |(name, values)| Annotation {
+            name,
+            values: values.unwrap_or_default(),
+        },
+    )(input)
+}
+
+/// The comments and annotations before an item, in any order.
+pub fn parse_preamble(input: &[u8]) -> IResult<&[u8], (Vec<String>, Vec<Annotation>)> {
+    fold_many0(
+        ws0(alt((
+            map(parse_comment, |comment| (comment, None)),
+            map(parse_annotation, |annotation| (None, Some(annotation))),
+        ))),
+        (Vec::new(), Vec::new()),
+        |(mut comments, mut annotations), (comment, annotation)| {
+            comments.extend(comment);
+            annotations.extend(annotation);
+            (comments, annotations)
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_annotation() {
+        assert_eq!(
+            parse_annotation(b"[Deprecated]"),
+            Ok((
+                &b""[..],
+                Annotation {
+                    name: vec!["Deprecated".to_owned()],
+                    values: vec![]
+                }
+            ))
+        );
+        assert_eq!(
+            parse_annotation(
+                b"[my.Annotation(1, -2.5e3, true, \"a \\\"b\\\"\", [1, 2], {\"k\": Color.RED}, Range(0, 10))]"
+            ),
+            Ok((
+                &b""[..],
+                Annotation {
+                    name: vec!["my".to_owned(), "Annotation".to_owned()],
+                    values: vec![
+                        AnnotationValue::Number("1".to_owned()),
+                        AnnotationValue::Number("-2.5e3".to_owned()),
+                        AnnotationValue::Bool(true),
+                        AnnotationValue::String("a \\\"b\\\"".to_owned()),
+                        AnnotationValue::List(vec![
+                            AnnotationValue::Number("1".to_owned()),
+                            AnnotationValue::Number("2".to_owned())
+                        ]),
+                        AnnotationValue::Map(vec![(
+                            AnnotationValue::String("k".to_owned()),
+                            AnnotationValue::Reference(vec!["Color".to_owned(), "RED".to_owned()])
+                        )]),
+                        AnnotationValue::Instance(Annotation {
+                            name: vec!["Range".to_owned()],
+                            values: vec![
+                                AnnotationValue::Number("0".to_owned()),
+                                AnnotationValue::Number("10".to_owned())
+                            ]
+                        })
+                    ]
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_preamble() {
+        assert_eq!(
+            parse_preamble(b"// Before\n[First]\n// Between \"quoted\"\n[Second]\ntype"),
+            Ok((
+                &b"type"[..],
+                (
+                    vec![" Before".to_owned(), " Between \"quoted\"".to_owned()],
+                    vec![
+                        Annotation {
+                            name: vec!["First".to_owned()],
+                            values: vec![]
+                        },
+                        Annotation {
+                            name: vec!["Second".to_owned()],
+                            values: vec![]
+                        }
+                    ]
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_annotations() {
+        assert_eq!(
+            parse_preamble(b"[First]\n[Second(\"\")]\ntype"),
+            Ok((
+                &b"type"[..],
+                (
+                    vec![],
+                    vec![
+                        Annotation {
+                            name: vec!["First".to_owned()],
+                            values: vec![]
+                        },
+                        Annotation {
+                            name: vec!["Second".to_owned()],
+                            values: vec![AnnotationValue::String("".to_owned())]
+                        }
+                    ]
+                )
+            ))
+        );
+    }
+}
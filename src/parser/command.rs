@@ -2,40 +2,52 @@ use crate::{
     ast::{Command, DataType},
     parser::{
         data_type::parse_type,
-        utils::{snake_case as parse_command_name, ws0},
+        utils::{
+            expect, identifier as parse_command_name, parse_comments, whitespace0, whitespace1, ws0,
+        },
+        IResult,
     },
 };
 
 use nom::{
     bytes::complete::tag,
-    character::complete::multispace1,
-    character::complete::{char, multispace0},
+    character::complete::char,
     combinator::map,
     multi::separated_list0,
     sequence::delimited,
-    sequence::{preceded, tuple},
-    IResult,
+    sequence::{pair, preceded, tuple},
 };
 
 pub fn parse_args(input: &[u8]) -> IResult<&[u8], Vec<DataType>> {
     delimited(
         char('('),
         ws0(separated_list0(ws0(char(',')), parse_type)),
-        char(')'),
+        expect("expected `)` after command arguments", char(')')),
     )(input)
 }
 
 pub fn parse_command(input: &[u8]) -> IResult<&[u8], Command> {
     map(
         tuple((
-            preceded(tag("command"), preceded(multispace1, parse_type)),
-            preceded(multispace1, parse_command_name),
-            preceded(multispace0, parse_args),
+            parse_comments,
+            preceded(
+                pair(tag("command"), whitespace1),
+                expect("expected command response type", parse_type),
+            ),
+            expect(
+                "expected command name",
+                preceded(whitespace1, parse_command_name),
+            ),
+            expect(
+                "expected `(` after command name",
+                preceded(whitespace0, parse_args),
+            ),
         )),
-        |(ty, name, args)| Command {
+        |(comments, ty, name, args)| Command {
             r_type: ty,
             name,
             args,
+            comments,
         },
     )(input)
 }
@@ -54,7 +66,8 @@ mod tests {
                 Command {
                     name: "count_rabbits".to_string(),
                     args: vec![],
-                    r_type: DataType::Uint32
+                    r_type: DataType::Uint32,
+                    comments: vec![]
                 }
             ))
         );
@@ -65,7 +78,8 @@ mod tests {
                 Command {
                     name: "count_rabbits".to_string(),
                     args: vec![DataType::Bool],
-                    r_type: DataType::Uint32
+                    r_type: DataType::Uint32,
+                    comments: vec![]
                 }
             ))
         );
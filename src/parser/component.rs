@@ -1,13 +1,17 @@
 use crate::{
-    ast::{Command, Component, Enum, Event, Member, Type},
+    ast::{Annotation, Command, Component, DataType, Enum, Event, Member, Type},
     parser::{
+        annotation::parse_preamble,
         command::parse_command,
+        data_type::parse_type as parse_data_type,
         event::parse_event,
         member::parse_member,
         r#enum::parse_enum,
         r#type::parse_type,
-        utils::camel_case as parse_component_name,
-        utils::{parse_comments, parse_u32, ws0, ws1},
+        recovery::{recover, unexpected},
+        utils::identifier as parse_component_name,
+        utils::{expect, parse_comments, parse_u32, punct, whitespace0, whitespace1, ws0},
+        IResult,
     },
 };
 
@@ -17,13 +21,13 @@ use nom::{
     character::complete::char,
     combinator::{map, map_res},
     multi::many1,
-    sequence::{delimited, preceded, terminated, tuple},
-    IResult,
+    sequence::{delimited, pair, preceded, terminated, tuple},
 };
 
 #[derive(Debug)]
 enum ComponentProperty {
     ID(u32),
+    Data(DataType),
     Member(Member),
     Command(Command),
     Event(Event),
@@ -35,10 +39,12 @@ enum ComponentProperty {
 struct ComponentBuilder {
     pub name: Option<String>,
     pub id: Option<u32>,
+    pub data: Option<DataType>,
     pub members: Vec<Member>,
     pub commands: Vec<Command>,
     pub events: Vec<Event>,
     pub comments: Vec<String>,
+    pub annotations: Vec<Annotation>,
     pub types: Vec<Type>,
     pub enums: Vec<Enum>,
 }
@@ -47,6 +53,7 @@ impl ComponentBuilder {
     pub fn with_property(mut self, property: ComponentProperty) -> Self {
         match property {
             ComponentProperty::ID(id) => self.id = Some(id),
+            ComponentProperty::Data(d) => self.data = Some(d),
             ComponentProperty::Member(m) => self.members.push(m),
             ComponentProperty::Event(e) => self.events.push(e),
             ComponentProperty::Command(c) => self.commands.push(c),
@@ -66,16 +73,23 @@ impl ComponentBuilder {
         self
     }
 
+    pub fn with_annotations(mut self, annotations: Vec<Annotation>) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
     pub fn build(self) -> Result<Component, &'static str> {
         let name = self.name.ok_or("Name could not be found")?;
         let id = self.id.ok_or("ID could not be found")?;
         Ok(Component {
             name,
             id,
+            data: self.data,
             members: self.members,
             commands: self.commands,
             events: self.events,
             comments: self.comments,
+            annotations: self.annotations,
             enums: self.enums,
             types: self.types,
         })
@@ -83,46 +97,92 @@ impl ComponentBuilder {
 }
 
 fn parse_id(input: &[u8]) -> IResult<&[u8], u32> {
-    preceded(tag("id"), preceded(ws0(char('=')), parse_u32))(input)
+    preceded(
+        tuple((parse_comments, tag("id"), ws0(char('=')))),
+        expect("expected component ID after `id =`", parse_u32),
+    )(input)
 }
 
-fn parse_direct_property(input: &[u8]) -> IResult<&[u8], ComponentProperty> {
-    terminated(
-        ws0(alt((
-            map(parse_id, ComponentProperty::ID),
-            map(parse_member, ComponentProperty::Member),
-            map(parse_command, ComponentProperty::Command),
-            map(parse_event, ComponentProperty::Event),
-        ))),
-        char(';'),
+fn parse_data(input: &[u8]) -> IResult<&[u8], DataType> {
+    preceded(
+        tuple((parse_comments, tag("data"), whitespace1)),
+        expect("expected type after `data`", parse_data_type),
     )(input)
 }
 
+fn semicolon<'a>(message: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], char> {
+    expect(message, preceded(whitespace0, punct(';')))
+}
+
+fn parse_direct_property(input: &[u8]) -> IResult<&[u8], ComponentProperty> {
+    ws0(alt((
+        map(
+            terminated(parse_id, semicolon("expected `;` after component ID")),
+            ComponentProperty::ID,
+        ),
+        map(
+            terminated(parse_data, semicolon("expected `;` after component data")),
+            ComponentProperty::Data,
+        ),
+        map(
+            terminated(parse_command, semicolon("expected `;` after command")),
+            ComponentProperty::Command,
+        ),
+        map(
+            terminated(parse_event, semicolon("expected `;` after event")),
+            ComponentProperty::Event,
+        ),
+    )))(input)
+}
+
 fn parse_property(input: &[u8]) -> IResult<&[u8], ComponentProperty> {
     ws0(alt((
         parse_direct_property,
         map(parse_type, ComponentProperty::Type),
         map(parse_enum, ComponentProperty::Enum),
+        map(
+            terminated(parse_member, semicolon("expected `;` after field ID")),
+            ComponentProperty::Member,
+        ),
     )))(input)
 }
 
 fn parse_properties(input: &[u8]) -> IResult<&[u8], Vec<ComponentProperty>> {
-    many1(ws0(parse_property))(input)
+    map(
+        many1(ws0(recover(alt((
+            parse_property,
+            unexpected("expected `}` or a component property"),
+        ))))),
+        |properties| properties.into_iter().flatten().collect(),
+    )(input)
 }
 
 fn parse_component_body(input: &[u8]) -> IResult<&[u8], Vec<ComponentProperty>> {
-    delimited(char('{'), ws0(parse_properties), char('}'))(input)
+    delimited(
+        expect("expected `{` after component name", punct('{')),
+        ws0(parse_properties),
+        expect(
+            "expected `}` or a component property",
+            preceded(parse_comments, punct('}')),
+        ),
+    )(input)
 }
 
 pub fn parse_component(input: &[u8]) -> IResult<&[u8], Component> {
     map_res(
         map(
             tuple((
-                ws0(parse_comments),
-                preceded(tag("component"), ws1(parse_component_name)),
+                ws0(parse_preamble),
+                preceded(
+                    pair(tag("component"), whitespace1),
+                    expect(
+                        "expected component name",
+                        terminated(parse_component_name, whitespace0),
+                    ),
+                ),
                 parse_component_body,
             )),
-            |(comments, name, properties)| {
+            |((comments, annotations), name, properties)| {
                 properties
                     .into_iter()
                     .fold(ComponentBuilder::default(), |acc, val| {
@@ -130,6 +190,7 @@ pub fn parse_component(input: &[u8]) -> IResult<&[u8], Component> {
                     })
                     .with_name(name)
                     .with_comments(comments)
+                    .with_annotations(annotations)
             },
         ),
         |builder| builder.build(),
@@ -199,6 +260,75 @@ mod tests {
             event Rabbit new_rabbit;
         }";
 
+    #[test]
+    fn test_parse_data_component() {
+        assert_eq!(
+            parse_component(b"component Position {\n\tid = 54;\n\tdata improbable.Coordinates;\n}"),
+            Ok((
+                &b""[..],
+                Component {
+                    annotations: vec![],
+                    data: Some(crate::ast::DataType::UserDefined(
+                        UserDefinedType::Unresolved(vec![
+                            "improbable".to_owned(),
+                            "Coordinates".to_owned()
+                        ])
+                    )),
+                    id: 54,
+                    comments: Vec::new(),
+                    name: "Position".to_string(),
+                    members: vec![],
+                    events: vec![],
+                    commands: vec![],
+                    enums: vec![],
+                    types: vec![]
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_commented_component() {
+        let (_, component) = parse_component(
+            b"/* Counts animals */ component AnimalCounter { // trailing
+                // Component ID
+                id = 1001; /* block */ // trailing
+                uint32 rabbits /* inline */ = 1; // trailing
+                // Counts platypus
+                command uint32 count_platypus(Field);
+                /* Block
+                   comment */
+                // A new rabbit
+                event Rabbit new_rabbit;
+            } // trailing",
+        )
+        .unwrap();
+        assert_eq!(component.id, 1001);
+        assert!(component.comments.is_empty());
+        assert!(component.members[0].comments.is_empty());
+        assert_eq!(component.commands[0].comments, vec![" Counts platypus"]);
+        assert_eq!(component.events[0].comments, vec![" A new rabbit"]);
+    }
+
+    #[test]
+    fn test_parse_component_with_digits_and_underscores() {
+        let (_, component) = parse_component(
+            b"component HTTPRequest_2 {
+                id = 1002;
+                Vector3F position_3d = 1;
+                event ABTestConfig config_changed2;
+                type Header_V1 { string key = 1; }
+                enum Method { GET = 0; POST_V2 = 1; }
+            }",
+        )
+        .unwrap();
+        assert_eq!(component.name, "HTTPRequest_2");
+        assert_eq!(component.members[0].name, "position_3d");
+        assert_eq!(component.events[0].name, "config_changed2");
+        assert_eq!(component.types[0].name, "Header_V1");
+        assert_eq!(component.enums[0].variants[1].name, "POST_V2");
+    }
+
     #[test]
     fn test_parse_component() {
         assert_eq!(
@@ -206,17 +336,21 @@ mod tests {
             Ok((
                 &b""[..],
                 Component {
+                    data: None,
+                    annotations: vec![],
                     id: 1001,
                     comments: Vec::new(),
                     name: "AnimalCounter".to_string(),
                     members: vec![
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Uint32,
                             name: "rabbits".to_owned(),
                             id: 1,
                             comments: vec![]
                         },
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Double,
                             name: "platypus".to_owned(),
                             id: 2,
@@ -226,15 +360,17 @@ mod tests {
                     events: vec![Event {
                         name: "new_rabbit".to_owned(),
                         r_type: crate::ast::DataType::UserDefined(UserDefinedType::Unresolved(
-                            "Rabbit".to_owned()
-                        ))
+                            vec!["Rabbit".to_owned()]
+                        )),
+                        comments: vec![]
                     }],
                     commands: vec![Command {
                         name: "count_platypus".to_owned(),
                         r_type: crate::ast::DataType::Uint32,
                         args: vec![crate::ast::DataType::UserDefined(
-                            UserDefinedType::Unresolved("Field".to_owned())
-                        )]
+                            UserDefinedType::Unresolved(vec!["Field".to_owned()])
+                        )],
+                        comments: vec![]
                     }],
                     enums: vec![],
                     types: vec![]
@@ -246,17 +382,21 @@ mod tests {
             Ok((
                 &b""[..],
                 Component {
+                    data: None,
+                    annotations: vec![],
                     id: 1001,
                     comments: vec![" This is used to count animals".to_owned()],
                     name: "AnimalCounter".to_string(),
                     members: vec![
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Uint32,
                             name: "rabbits".to_owned(),
                             id: 1,
                             comments: vec![" This is used to count rabbits".to_owned()]
                         },
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Double,
                             name: "platypus".to_owned(),
                             id: 2,
@@ -266,15 +406,17 @@ mod tests {
                     events: vec![Event {
                         name: "new_rabbit".to_owned(),
                         r_type: crate::ast::DataType::UserDefined(UserDefinedType::Unresolved(
-                            "Rabbit".to_owned()
-                        ))
+                            vec!["Rabbit".to_owned()]
+                        )),
+                        comments: vec![]
                     }],
                     commands: vec![Command {
                         name: "count_platypus".to_owned(),
                         r_type: crate::ast::DataType::Uint32,
                         args: vec![crate::ast::DataType::UserDefined(
-                            UserDefinedType::Unresolved("Field".to_owned())
-                        )]
+                            UserDefinedType::Unresolved(vec!["Field".to_owned()])
+                        )],
+                        comments: vec![]
                     }],
                     enums: vec![],
                     types: vec![]
@@ -286,17 +428,21 @@ mod tests {
             Ok((
                 &b""[..],
                 Component {
+                    data: None,
+                    annotations: vec![],
                     comments: vec![" This is used to count animals".to_owned()],
                     name: "AnimalCounter".to_string(),
                     id: 1001,
                     members: vec![
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Uint32,
                             name: "rabbits".to_owned(),
                             id: 1,
                             comments: vec![" This is used to count rabbits".to_owned()]
                         },
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Double,
                             name: "platypus".to_owned(),
                             id: 2,
@@ -306,26 +452,31 @@ mod tests {
                     events: vec![Event {
                         name: "new_rabbit".to_owned(),
                         r_type: crate::ast::DataType::UserDefined(UserDefinedType::Unresolved(
-                            "Rabbit".to_owned()
-                        ))
+                            vec!["Rabbit".to_owned()]
+                        )),
+                        comments: vec![]
                     }],
                     commands: vec![Command {
                         name: "count_platypus".to_owned(),
                         r_type: crate::ast::DataType::Uint32,
                         args: vec![crate::ast::DataType::UserDefined(
-                            UserDefinedType::Unresolved("Field".to_owned())
-                        )]
+                            UserDefinedType::Unresolved(vec!["Field".to_owned()])
+                        )],
+                        comments: vec![]
                     }],
                     enums: vec![Enum {
+                        annotations: vec![],
                         comments: vec![],
                         name: "LifeState".to_owned(),
                         variants: vec![
                             Variant {
+                                annotations: vec![],
                                 comments: vec![],
                                 name: "ALIVE".to_owned(),
                                 id: 0
                             },
                             Variant {
+                                annotations: vec![],
                                 comments: vec![],
                                 name: "DEAD".to_owned(),
                                 id: 1
@@ -333,42 +484,49 @@ mod tests {
                         ]
                     }],
                     types: vec![Type {
+                        annotations: vec![],
                         comments: vec![],
                         name: "Rabbit".to_owned(),
                         members: vec![
                             Member {
+                                annotations: vec![],
                                 comments: vec![],
                                 m_type: crate::ast::DataType::String,
                                 name: "name".to_owned(),
                                 id: 1
                             },
                             Member {
+                                annotations: vec![],
                                 comments: vec![],
                                 m_type: crate::ast::DataType::UserDefined(
-                                    UserDefinedType::Unresolved("LifeState".to_owned())
+                                    UserDefinedType::Unresolved(vec!["LifeState".to_owned()])
                                 ),
                                 name: "life_state".to_owned(),
                                 id: 2
                             },
                             Member {
+                                annotations: vec![],
                                 comments: vec![],
                                 m_type: crate::ast::DataType::UserDefined(
-                                    UserDefinedType::Unresolved("Gender".to_owned())
+                                    UserDefinedType::Unresolved(vec!["Gender".to_owned()])
                                 ),
                                 name: "gender".to_owned(),
                                 id: 3
                             }
                         ],
                         enums: vec![Enum {
+                            annotations: vec![],
                             comments: vec![],
                             name: "Gender".to_owned(),
                             variants: vec![
                                 Variant {
+                                    annotations: vec![],
                                     comments: vec![],
                                     name: "MALE".to_owned(),
                                     id: 1
                                 },
                                 Variant {
+                                    annotations: vec![],
                                     comments: vec![],
                                     name: "FEMALE".to_owned(),
                                     id: 2
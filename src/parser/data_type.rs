@@ -1,78 +1,127 @@
 use crate::{
     ast::{DataType, UserDefinedType},
-    parser::utils::{uppercase, ws0},
+    parser::{
+        utils::{expect, identifier, ws0},
+        IResult,
+    },
 };
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    bytes::complete::take_while,
-    character::complete::char,
-    character::is_alphabetic,
-    combinator::{map, map_res, value},
+    character::complete::{char, satisfy},
+    combinator::{map, not, value},
+    error::{VerboseError, VerboseErrorKind},
+    multi::separated_list1,
     sequence::separated_pair,
-    sequence::{delimited, pair},
-    IResult,
+    sequence::{delimited, pair, terminated},
+    Err,
 };
 
 pub fn parse_one_generic(input: &[u8]) -> IResult<&[u8], DataType> {
-    delimited(char('<'), ws0(parse_type_without_generics), char('>'))(input)
+    delimited(
+        char('<'),
+        ws0(expect("expected a type", parse_type)),
+        expect("expected `>`", char('>')),
+    )(input)
 }
 
 pub fn parse_two_generics(input: &[u8]) -> IResult<&[u8], (DataType, DataType)> {
     delimited(
         char('<'),
         ws0(separated_pair(
-            parse_type_without_generics,
-            ws0(char(',')),
-            parse_type_without_generics,
+            expect("expected a key type", parse_type),
+            expect("expected `,` after the key type", ws0(char(','))),
+            expect("expected a value type", parse_type),
         )),
-        char('>'),
+        expect("expected `>`", char('>')),
     )(input)
 }
 
-pub fn parse_primitive(input: &[u8]) -> IResult<&[u8], DataType> {
-    alt((
-        value(DataType::Bool, tag("bool")),
-        value(DataType::Float, tag("float")),
-        value(DataType::Bytes, tag("bytes")),
-        value(DataType::Int32, tag("int32")),
-        value(DataType::Int64, tag("int64")),
-        value(DataType::String, tag("string")),
-        value(DataType::Double, tag("double")),
-        value(DataType::Uint32, tag("uint32")),
-        value(DataType::Uint64, tag("uint64")),
-        value(DataType::SInt32, tag("sint32")),
-        value(DataType::SInt64, tag("sint64")),
-        value(DataType::Fixed32, tag("fixed32")),
-        value(DataType::Fixed64, tag("fixed64")),
-        value(DataType::SFixed32, tag("sfixed32")),
-        value(DataType::SFixed64, tag("sfixed64")),
-        value(DataType::EntityID, tag("EntityId")),
-        value(DataType::Entity, tag("Entity")),
-    ))(input)
+/// Fails without backtracking when the parsed type nests collections in a way
+/// the schema language forbids.
+fn forbid<'a, F>(
+    mut parser: F,
+    forbidden: fn(&DataType) -> Option<&'static str>,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], DataType>
+where
+    F: FnMut(&'a [u8]) -> IResult<&'a [u8], DataType>,
+{
+    move |input| {
+        let (rest, data_type) = parser(input)?;
+        match forbidden(&data_type) {
+            Some(message) => Err(Err::Failure(VerboseError {
+                errors: vec![(input, VerboseErrorKind::Context(message))],
+            })),
+            None => Ok((rest, data_type)),
+        }
+    }
 }
 
-pub fn parse_user_type(input: &[u8]) -> IResult<&[u8], String> {
-    map(
-        pair(
-            uppercase,
-            map_res(take_while(is_alphabetic), std::str::from_utf8),
-        ),
-        |(first_letter, rest)| first_letter.to_string() + rest,
+fn is_collection(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Map(_, _) | DataType::List(_) | DataType::Option(_)
+    )
+}
+
+/// Primitive type keywords, which must not be followed by further identifier
+/// characters so that names such as `EntityAcl` or `int32Pair` stay user types.
+pub fn parse_primitive(input: &[u8]) -> IResult<&[u8], DataType> {
+    terminated(
+        alt((
+            value(DataType::Bool, tag("bool")),
+            value(DataType::Float, tag("float")),
+            value(DataType::Bytes, tag("bytes")),
+            value(DataType::Int32, tag("int32")),
+            value(DataType::Int64, tag("int64")),
+            value(DataType::String, tag("string")),
+            value(DataType::Double, tag("double")),
+            value(DataType::Uint32, tag("uint32")),
+            value(DataType::Uint64, tag("uint64")),
+            value(DataType::SInt32, tag("sint32")),
+            value(DataType::SInt64, tag("sint64")),
+            value(DataType::Fixed32, tag("fixed32")),
+            value(DataType::Fixed64, tag("fixed64")),
+            value(DataType::SFixed32, tag("sfixed32")),
+            value(DataType::SFixed64, tag("sfixed64")),
+            value(DataType::EntityID, tag("EntityId")),
+            value(DataType::Entity, tag("Entity")),
+        )),
+        not(satisfy(|c| c.is_ascii_alphanumeric() || c == '_')),
     )(input)
 }
 
+pub fn parse_user_type(input: &[u8]) -> IResult<&[u8], Vec<String>> {
+    separated_list1(char('.'), identifier)(input)
+}
+
 pub fn parse_generic_type(input: &[u8]) -> IResult<&[u8], DataType> {
     alt((
-        map(pair(tag("map"), parse_two_generics), |(_, generics)| {
-            DataType::Map(Box::new(generics.0), Box::new(generics.1))
-        }),
+        forbid(
+            map(pair(tag("map"), parse_two_generics), |(_, generics)| {
+                DataType::Map(Box::new(generics.0), Box::new(generics.1))
+            }),
+            |map| match map {
+                DataType::Map(key, _) if is_collection(key) => {
+                    Some("the key of a `map` can't be a `map`, `list` or `option`")
+                }
+                _ => None,
+            },
+        ),
         map(pair(tag("list"), parse_one_generic), |(_, generic)| {
             DataType::List(Box::new(generic))
         }),
-        map(pair(tag("option"), parse_one_generic), |(_, generic)| {
-            DataType::Option(Box::new(generic))
-        }),
+        forbid(
+            map(pair(tag("option"), parse_one_generic), |(_, generic)| {
+                DataType::Option(Box::new(generic))
+            }),
+            |option| match option {
+                DataType::Option(inner) if matches!(**inner, DataType::Option(_)) => {
+                    Some("an `option` can't directly contain another `option`")
+                }
+                _ => None,
+            },
+        ),
     ))(input)
 }
 
@@ -87,14 +136,17 @@ pub fn parse_type_without_generics(input: &[u8]) -> IResult<&[u8], DataType> {
 }
 
 pub fn parse_type(input: &[u8]) -> IResult<&[u8], DataType> {
-    alt((parse_type_without_generics, parse_generic_type))(input)
+    alt((parse_generic_type, parse_type_without_generics))(input)
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use nom::{error::Error, error::ErrorKind, Err};
+    use nom::{
+        error::{ErrorKind, VerboseError, VerboseErrorKind},
+        Err,
+    };
 
     #[test]
     fn test_parse_type() {
@@ -119,7 +171,30 @@ mod tests {
             parse_type(b"CustomComponent"),
             Ok((
                 &b""[..],
-                DataType::UserDefined(UserDefinedType::Unresolved("CustomComponent".to_string()))
+                DataType::UserDefined(UserDefinedType::Unresolved(vec![
+                    "CustomComponent".to_string()
+                ]))
+            ))
+        );
+        assert_eq!(
+            parse_type(b"improbable.Coordinates"),
+            Ok((
+                &b""[..],
+                DataType::UserDefined(UserDefinedType::Unresolved(vec![
+                    "improbable".to_string(),
+                    "Coordinates".to_string()
+                ]))
+            ))
+        );
+        assert_eq!(
+            parse_type(b"improbable.ComponentInterest.Query"),
+            Ok((
+                &b""[..],
+                DataType::UserDefined(UserDefinedType::Unresolved(vec![
+                    "improbable".to_string(),
+                    "ComponentInterest".to_string(),
+                    "Query".to_string()
+                ]))
             ))
         );
         assert_eq!(
@@ -139,10 +214,112 @@ mod tests {
         );
         assert_eq!(
             parse_primitive(b"customComponent"),
-            Err(Err::Error(Error::new(
-                &b"customComponent"[..],
-                ErrorKind::Tag
-            )))
+            Err(Err::Error(VerboseError {
+                errors: vec![
+                    (
+                        &b"customComponent"[..],
+                        VerboseErrorKind::Nom(ErrorKind::Tag)
+                    ),
+                    (
+                        &b"customComponent"[..],
+                        VerboseErrorKind::Nom(ErrorKind::Alt)
+                    ),
+                ]
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_identifier_names() {
+        let user = |names: &[&str]| {
+            Ok((
+                &b""[..],
+                DataType::UserDefined(UserDefinedType::Unresolved(
+                    names.iter().map(|n| n.to_string()).collect(),
+                )),
+            ))
+        };
+        assert_eq!(parse_type(b"Vector3F"), user(&["Vector3F"]));
+        assert_eq!(
+            parse_type(b"net.HTTPRequest"),
+            user(&["net", "HTTPRequest"])
+        );
+        assert_eq!(
+            parse_type(b"ab_tests.ABTestConfig.My_Type"),
+            user(&["ab_tests", "ABTestConfig", "My_Type"])
+        );
+        assert_eq!(
+            parse_type(b"improbable.EntityAcl"),
+            user(&["improbable", "EntityAcl"])
+        );
+        assert_eq!(parse_type(b"EntityAcl"), user(&["EntityAcl"]));
+        assert_eq!(parse_type(b"int32_pair"), user(&["int32_pair"]));
+        assert_eq!(parse_type(b"listing"), user(&["listing"]));
+        assert_eq!(
+            parse_type(b"list<Vector3F>"),
+            Ok((
+                &b""[..],
+                DataType::List(Box::new(user(&["Vector3F"]).unwrap().1))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_generics() {
+        let list = |t| DataType::List(Box::new(t));
+        assert_eq!(
+            parse_type(b"list<map<uint32, list<string>>>"),
+            Ok((
+                &b""[..],
+                list(DataType::Map(
+                    Box::new(DataType::Uint32),
+                    Box::new(list(DataType::String))
+                ))
+            ))
+        );
+        assert_eq!(
+            parse_type(b"option< list<improbable.Coordinates> >"),
+            Ok((
+                &b""[..],
+                DataType::Option(Box::new(list(DataType::UserDefined(
+                    UserDefinedType::Unresolved(vec![
+                        "improbable".to_owned(),
+                        "Coordinates".to_owned()
+                    ])
+                ))))
+            ))
+        );
+        assert_eq!(
+            DataType::Map(
+                Box::new(DataType::String),
+                Box::new(list(DataType::Option(Box::new(DataType::Bool))))
+            )
+            .rust_type(),
+            "HashMap<String, Vec<Option<bool>>>"
+        );
+    }
+
+    #[test]
+    fn test_reject_forbidden_generics() {
+        let failure = |source: &'static [u8], message| {
+            Err(Err::Failure(VerboseError {
+                errors: vec![(source, VerboseErrorKind::Context(message))],
+            }))
+        };
+        assert_eq!(
+            parse_type(b"option<option<bool>>"),
+            failure(
+                b"option<option<bool>>",
+                "an `option` can't directly contain another `option`"
+            )
+        );
+        assert_eq!(
+            parse_type(b"map<list<bool>, bool>"),
+            failure(
+                b"map<list<bool>, bool>",
+                "the key of a `map` can't be a `map`, `list` or `option`"
+            )
         );
+        assert!(parse_type(b"option<list<option<bool>>>").is_ok());
     }
 }
@@ -1,42 +1,70 @@
 use crate::{
     ast::{Enum, Variant},
     parser::{
-        utils::{camel_case as parse_enum_name, parse_comments, ws0, ws1},
+        annotation::parse_preamble,
+        recovery::{recover, unexpected},
+        utils::{
+            expect, identifier as parse_enum_name, parse_comments, punct, whitespace0, whitespace1,
+            ws0,
+        },
         variant::parse_variant,
+        IResult,
     },
 };
 use nom::{
+    branch::alt,
     bytes::complete::tag,
-    character::complete::{char, multispace0},
     combinator::map,
-    multi::separated_list1,
+    multi::many1,
     sequence::delimited,
-    sequence::{preceded, terminated, tuple},
-    IResult,
+    sequence::{pair, preceded, terminated, tuple},
 };
 
 fn parse_variants(input: &[u8]) -> IResult<&[u8], Vec<Variant>> {
-    separated_list1(
-        multispace0,
-        terminated(parse_variant, tuple((multispace0, char(';')))),
+    map(
+        many1(ws0(recover(alt((
+            terminated(
+                parse_variant,
+                expect(
+                    "expected `;` after enum value",
+                    tuple((whitespace0, punct(';'))),
+                ),
+            ),
+            unexpected("expected `}` or an enum value"),
+        ))))),
+        |variants| variants.into_iter().flatten().collect(),
     )(input)
 }
 
 fn parse_enum_body(input: &[u8]) -> IResult<&[u8], Vec<Variant>> {
-    delimited(char('{'), ws0(parse_variants), char('}'))(input)
+    delimited(
+        expect("expected `{` after enum name", punct('{')),
+        ws0(parse_variants),
+        expect(
+            "expected `}` or an enum value",
+            preceded(parse_comments, punct('}')),
+        ),
+    )(input)
 }
 
 pub fn parse_enum(input: &[u8]) -> IResult<&[u8], Enum> {
     map(
         tuple((
-            parse_comments,
-            preceded(tag("enum"), ws1(parse_enum_name)),
+            parse_preamble,
+            preceded(
+                pair(tag("enum"), whitespace1),
+                expect(
+                    "expected enum name",
+                    terminated(parse_enum_name, whitespace0),
+                ),
+            ),
             parse_enum_body,
         )),
-        |(comments, name, variants)| Enum {
+        |((comments, annotations), name, variants)| Enum {
             name,
             variants,
             comments,
+            annotations,
         },
     )(input)
 }
@@ -45,6 +73,34 @@ pub fn parse_enum(input: &[u8]) -> IResult<&[u8], Enum> {
 mod tests {
 
     use super::*;
+    use crate::ast::Annotation;
+
+    #[test]
+    fn test_parse_annotated_enum() {
+        assert_eq!(
+            parse_enum(b"[Flags]\nenum AnimalCounter {\n\t[Deprecated]\n\tRABBITS_COUNTER = 1;\n}"),
+            Ok((
+                &b""[..],
+                Enum {
+                    annotations: vec![Annotation {
+                        name: vec!["Flags".to_owned()],
+                        values: vec![]
+                    }],
+                    comments: Vec::new(),
+                    name: "AnimalCounter".to_string(),
+                    variants: vec![Variant {
+                        annotations: vec![Annotation {
+                            name: vec!["Deprecated".to_owned()],
+                            values: vec![]
+                        }],
+                        name: "RABBITS_COUNTER".to_owned(),
+                        id: 1,
+                        comments: vec![]
+                    }]
+                }
+            ))
+        );
+    }
 
     #[test]
     fn test_parse_enum() {
@@ -55,15 +111,18 @@ mod tests {
             Ok((
                 &b""[..],
                 Enum {
+                    annotations: vec![],
                     comments: Vec::new(),
                     name: "AnimalCounter".to_string(),
                     variants: vec![
                         Variant {
+                            annotations: vec![],
                             name: "RABBITS_COUNTER".to_owned(),
                             id: 1,
                             comments: vec![]
                         },
                         Variant {
+                            annotations: vec![],
                             name: "PLATYPUS_COUNTER".to_owned(),
                             id: 2,
                             comments: vec![]
@@ -77,15 +136,18 @@ mod tests {
             Ok((
                 &b""[..],
                 Enum {
+annotations: vec![],
                     comments: vec![" This is used to count animals".to_owned()],
                     name: "AnimalCounter".to_string(),
                     variants: vec![
                         Variant {
+annotations: vec![],
                             name: "RABBITS_COUNTER".to_owned(),
                             id: 1,
                             comments: vec![" This is used to count rabbits".to_owned()]
                         },
                         Variant {
+annotations: vec![],
                             name: "PLATYPUS_COUNTER".to_owned(),
                             id: 2,
                             comments: vec![" This is used to count platypus".to_owned()]
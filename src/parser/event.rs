@@ -2,21 +2,35 @@ use crate::{
     ast::Event,
     parser::{
         data_type::parse_type,
-        utils::{snake_case as parse_event_name, ws1},
+        utils::{expect, identifier as parse_event_name, parse_comments, whitespace1},
+        IResult,
     },
 };
 
 use nom::{
     bytes::complete::tag,
     combinator::map,
-    sequence::{pair, preceded},
-    IResult,
+    sequence::{pair, preceded, tuple},
 };
 
 pub fn parse_event(input: &[u8]) -> IResult<&[u8], Event> {
     map(
-        pair(preceded(tag("event"), ws1(parse_type)), parse_event_name),
-        |(ty, name)| Event { r_type: ty, name },
+        tuple((
+            parse_comments,
+            preceded(
+                pair(tag("event"), whitespace1),
+                expect("expected event type", parse_type),
+            ),
+            expect(
+                "expected event name",
+                preceded(whitespace1, parse_event_name),
+            ),
+        )),
+        |(comments, ty, name)| Event {
+            r_type: ty,
+            name,
+            comments,
+        },
     )(input)
 }
 
@@ -35,7 +49,8 @@ mod tests {
                 &b""[..],
                 Event {
                     name: "count_rabbits".to_string(),
-                    r_type: DataType::Uint32
+                    r_type: DataType::Uint32,
+                    comments: vec![]
                 }
             ))
         );
@@ -0,0 +1,45 @@
+use nom::{
+    bytes::complete::{is_not, tag},
+    character::complete::char,
+    combinator::map_res,
+    sequence::{delimited, pair},
+};
+
+use crate::parser::{
+    utils::{expect, punct, whitespace0, whitespace1},
+    IResult,
+};
+
+pub fn parse_import_path(input: &[u8]) -> IResult<&[u8], String> {
+    map_res(
+        delimited(char('"'), is_not("\"\n\r"), char('"')),
+        |path: &[u8]| std::str::from_utf8(path).map(|s| s.to_string()),
+    )(input)
+}
+
+pub fn parse_import(input: &[u8]) -> IResult<&[u8], String> {
+    delimited(
+        pair(tag("import"), whitespace1),
+        expect("expected quoted import path", parse_import_path),
+        expect("expected `;` after import", pair(whitespace0, punct(';'))),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_import() {
+        assert_eq!(
+            parse_import(b"import \"improbable/standard_library.schema\";"),
+            Ok((&b""[..], "improbable/standard_library.schema".to_string()))
+        );
+        assert_eq!(
+            parse_import(b"import \"base.schema\" ;"),
+            Ok((&b""[..], "base.schema".to_string()))
+        );
+        assert!(parse_import(b"import base.schema;").is_err());
+    }
+}
@@ -1,33 +1,41 @@
 use nom::{
-    character::complete::{char, multispace1},
+    character::complete::char,
     combinator::map,
-    sequence::{pair, separated_pair},
-    IResult,
+    sequence::{pair, preceded, separated_pair, tuple},
 };
 
 use crate::{
     ast::{DataType, Member},
     parser::{
+        annotation::parse_preamble,
         data_type::parse_type,
-        utils::{parse_comments, parse_u32, snake_case as parse_member_name, ws0},
+        utils::{expect, identifier as parse_member_name, parse_u32, whitespace1, ws0},
+        IResult,
     },
 };
 
 fn parse_member_type_name(input: &[u8]) -> IResult<&[u8], (DataType, String)> {
-    separated_pair(parse_type, multispace1, parse_member_name)(input)
+    separated_pair(parse_type, whitespace1, parse_member_name)(input)
 }
 
 pub fn parse_member(input: &[u8]) -> IResult<&[u8], Member> {
     map(
-        pair(
-            parse_comments,
-            separated_pair(parse_member_type_name, ws0(char('=')), parse_u32),
-        ),
-        |(comments, ((ty, name), id))| Member {
+        tuple((
+            parse_preamble,
+            pair(
+                parse_member_type_name,
+                preceded(
+                    expect("expected `=` after field name", ws0(char('='))),
+                    expect("expected field ID after `=`", parse_u32),
+                ),
+            ),
+        )),
+        |((comments, annotations), ((ty, name), id))| Member {
             m_type: ty,
             name,
             id,
             comments,
+            annotations,
         },
     )(input)
 }
@@ -46,6 +54,7 @@ mod tests {
             Ok((
                 &b""[..],
                 Member {
+                    annotations: vec![],
                     comments: Vec::new(),
                     name: "rabbits".to_string(),
                     m_type: DataType::Uint32,
@@ -58,6 +67,7 @@ mod tests {
             Ok((
                 &b""[..],
                 Member {
+                    annotations: vec![],
                     comments: vec![" This is the number of rabbits".to_string()],
                     name: "rabbits".to_string(),
                     m_type: DataType::Uint32,
@@ -1,11 +1,19 @@
+pub mod annotation;
 pub mod command;
 pub mod component;
 pub mod data_type;
 pub mod r#enum;
 pub mod event;
+pub mod import;
 pub mod member;
 pub mod package_name;
+pub mod recovery;
 pub mod schema_file;
 pub mod r#type;
 pub mod utils;
 pub mod variant;
+
+use nom::error::VerboseError;
+
+/// Parser result carrying the context needed to build a `Diagnostic`.
+pub type IResult<I, O, E = VerboseError<I>> = nom::IResult<I, O, E>;
@@ -1,22 +1,30 @@
 use nom::{
     bytes::complete::tag,
-    character::complete::{char, multispace0, multispace1},
+    character::complete::char,
+    error::context,
     multi::separated_list1,
     sequence::{delimited, pair},
-    IResult,
 };
 
-use crate::parser::utils::snake_case;
+use crate::parser::IResult;
+
+use crate::parser::utils::{expect, identifier, punct, whitespace0, whitespace1};
 
 pub fn parse_package_components(input: &[u8]) -> IResult<&[u8], Vec<String>> {
-    separated_list1(char('.'), snake_case)(input)
+    separated_list1(char('.'), identifier)(input)
 }
 
 pub fn parse_package_name(input: &[u8]) -> IResult<&[u8], Vec<String>> {
-    delimited(
-        pair(tag("package"), multispace1),
-        parse_package_components,
-        pair(multispace0, char(';')),
+    context(
+        "expected `package` declaration",
+        delimited(
+            pair(tag("package"), whitespace1),
+            expect("expected package name", parse_package_components),
+            expect(
+                "expected `;` after package name",
+                pair(whitespace0, punct(';')),
+            ),
+        ),
     )(input)
 }
 
@@ -0,0 +1,119 @@
+use std::cell::RefCell;
+
+use nom::{
+    error::{ErrorKind, VerboseError, VerboseErrorKind},
+    Err,
+};
+
+use crate::parser::{utils::parse_comments, IResult};
+
+thread_local! {
+    static RECOVERED: RefCell<Option<Vec<(usize, String)>>> = const { RefCell::new(None) };
+}
+
+/// The innermost context of a parser failure and the input where it started,
+/// falling back to the deepest error when no context was attached.
+pub fn describe<'a>(error: &VerboseError<&'a [u8]>) -> Option<(&'a [u8], String)> {
+    error
+        .errors
+        .iter()
+        .find_map(|(input, kind)| match kind {
+            VerboseErrorKind::Context(context) => Some((*input, context.to_string())),
+            _ => None,
+        })
+        .or_else(|| match error.errors.first() {
+            Some((input, VerboseErrorKind::Char(c))) => Some((*input, format!("expected `{}`", c))),
+            Some((input, _)) => Some((*input, "invalid syntax".to_string())),
+            None => None,
+        })
+}
+
+/// Runs `f`, returning every failure recovered by `recover` while it ran as
+/// the length of the input left at the failure and its message.
+pub fn recovering<T, F: FnOnce() -> T>(f: F) -> (T, Vec<(usize, String)>) {
+    let outer = RECOVERED.with(|r| r.replace(Some(Vec::new())));
+    let result = f();
+    let recovered = RECOVERED.with(|r| r.replace(outer));
+    (result, recovered.unwrap_or_default())
+}
+
+/// Skips the rest of a malformed statement: up to and including the next `;`
+/// or block at the current nesting level, stopping before an unmatched `}`.
+fn skip_statement(input: &[u8]) -> &[u8] {
+    let mut depth = 0usize;
+    for (i, c) in input.iter().enumerate() {
+        match (c, depth) {
+            (b';', 0) => return &input[i + 1..],
+            (b'}', 0) => return &input[i..],
+            (b'}', 1) => return &input[i + 1..],
+            (b'}', _) => depth -= 1,
+            (b'{', _) => depth += 1,
+            _ => {}
+        }
+    }
+    &input[input.len()..]
+}
+
+/// Turns a committed failure of `parser` into `None`, recording it and
+/// resynchronising after the malformed statement so parsing can go on.
+/// Outside of `recovering` the failure is passed through untouched.
+pub fn recover<'a, O, F>(mut parser: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Option<O>>
+where
+    F: FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    move |input| match parser(input) {
+        Ok((rest, output)) => Ok((rest, Some(output))),
+        Err(Err::Failure(error)) => RECOVERED.with(|r| match r.borrow_mut().as_mut() {
+            Some(recovered) => {
+                let (position, message) =
+                    describe(&error).unwrap_or_else(|| (input, "invalid syntax".to_string()));
+                recovered.push((position.len(), message));
+                Ok((skip_statement(position), None))
+            }
+            None => Err(Err::Failure(error)),
+        }),
+        Err(error) => Err(error),
+    }
+}
+
+/// Fails with `message` on anything but the end of the input or of the
+/// enclosing block, so unknown statements get recovered from.
+pub fn unexpected<'a, O>(message: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O> {
+    move |input| {
+        let (rest, _) = parse_comments(input)?;
+        if rest.is_empty() || rest[0] == b'}' {
+            Err(Err::Error(VerboseError {
+                errors: vec![(rest, VerboseErrorKind::Nom(ErrorKind::Verify))],
+            }))
+        } else {
+            Err(Err::Failure(VerboseError {
+                errors: vec![(rest, VerboseErrorKind::Context(message))],
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_skip_statement() {
+        assert_eq!(
+            skip_statement(b"uint32 x 1; uint32 y = 2;"),
+            b" uint32 y = 2;"
+        );
+        assert_eq!(skip_statement(b"uint32 x = 1\n}"), b"}");
+        assert_eq!(skip_statement(b"1Bad { uint32 x = 1; } type"), b" type");
+    }
+
+    #[test]
+    fn test_recover_outside_recovering() {
+        let mut parser = recover(unexpected::<()>("expected `type`"));
+        assert!(matches!(parser(b"struct"), Err(Err::Failure(_))));
+        let (result, recovered) = recovering(|| parser(b"struct; type"));
+        assert_eq!(result, Ok((&b" type"[..], None)));
+        assert_eq!(recovered, vec![(12, "expected `type`".to_string())]);
+    }
+}
@@ -1,17 +1,31 @@
 use crate::{
     ast::{Component, Enum, SchemaFile, Type},
+    diagnostic::Diagnostic,
     parser::{
-        component::parse_component, package_name::parse_package_name, r#enum::parse_enum,
-        r#type::parse_type, utils::ws0,
+        component::parse_component,
+        import::parse_import,
+        package_name::parse_package_name,
+        r#enum::parse_enum,
+        r#type::parse_type,
+        recovery::{recover, unexpected},
+        utils::{expect, parse_comments, ws0},
+        IResult,
     },
 };
 
-use nom::{branch::alt, combinator::map, multi::many0, sequence::pair, IResult};
+use nom::{
+    branch::alt,
+    combinator::{eof, map},
+    multi::many0,
+    sequence::{preceded, tuple},
+};
 
 #[derive(Default)]
 pub struct SchemaFileBuilder {
     pub package_name: Option<Vec<String>>,
     pub name: Option<String>,
+    pub path: Option<String>,
+    pub imports: Vec<String>,
     pub types: Vec<Type>,
     pub enums: Vec<Enum>,
     pub components: Vec<Component>,
@@ -44,12 +58,25 @@ impl SchemaFileBuilder {
         self
     }
 
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn with_imports(mut self, imports: Vec<String>) -> Self {
+        self.imports = imports;
+        self
+    }
+
     pub fn build(self) -> Result<SchemaFile, &'static str> {
         let name = self.name.ok_or("Name could not be found")?;
         let package_name = self.package_name.ok_or("Package Name could not be found")?;
+        let path = self.path.unwrap_or_else(|| name.clone() + ".schema");
         Ok(SchemaFile {
             package_name,
             name,
+            path,
+            imports: self.imports,
             components: self.components,
             types: self.types,
             enums: self.enums,
@@ -66,17 +93,45 @@ fn parse_model(input: &[u8]) -> IResult<&[u8], SchemaModel> {
 }
 
 fn parse_models(input: &[u8]) -> IResult<&[u8], Vec<SchemaModel>> {
-    many0(ws0(parse_model))(input)
+    map(
+        many0(ws0(recover(alt((
+            parse_model,
+            unexpected("expected `type`, `component` or `enum`"),
+        ))))),
+        |models| models.into_iter().flatten().collect(),
+    )(input)
 }
 
-pub fn parse_schema(input: &[u8]) -> IResult<&[u8], SchemaFileBuilder> {
+fn parse_imports(input: &[u8]) -> IResult<&[u8], Vec<String>> {
+    many0(preceded(parse_comments, ws0(parse_import)))(input)
+}
+
+/// Parses a whole schema file, reporting every error recovered from along
+/// the way.
+pub fn parse_schema<S: AsRef<str>>(
+    file: S,
+    source: &[u8],
+) -> Result<SchemaFileBuilder, Vec<Diagnostic>> {
+    Diagnostic::run(file, source, schema_file)
+}
+
+fn schema_file(input: &[u8]) -> IResult<&[u8], SchemaFileBuilder> {
     map(
-        ws0(pair(parse_package_name, ws0(parse_models))),
-        |(package_name, models)| {
+        ws0(tuple((
+            preceded(parse_comments, parse_package_name),
+            ws0(parse_imports),
+            ws0(parse_models),
+            expect(
+                "expected `type`, `component` or `enum`",
+                preceded(parse_comments, eof),
+            ),
+        ))),
+        |(package_name, imports, models, _)| {
             models
                 .into_iter()
                 .fold(SchemaFileBuilder::default(), |acc, val| acc.with_model(val))
                 .with_package_name(package_name)
+                .with_imports(imports)
         },
     )(input)
 }
@@ -84,7 +139,7 @@ pub fn parse_schema(input: &[u8]) -> IResult<&[u8], SchemaFileBuilder> {
 #[cfg(test)]
 mod tests {
 
-    use crate::ast::*;
+    use crate::{ast::*, parser::recovery::recovering};
 
     use super::*;
 
@@ -117,9 +172,65 @@ mod tests {
         }
     ";
 
+    const IMPORTING_COMPONENT: &str = "
+        package io.nebulis.physics;
+
+        import \"improbable/standard_library.schema\";
+        import \"io/nebulis/base.schema\";
+
+        component Velocity {
+            id = 1200;
+            Coordinates value = 1;
+        }
+    ";
+
+    #[test]
+    fn test_parse_schema_imports() {
+        let (rest, schema_builder) = schema_file(IMPORTING_COMPONENT.as_bytes()).unwrap();
+        let schema = schema_builder.with_name("velocity".to_owned()).build();
+        assert_eq!(rest, &b""[..]);
+        assert_eq!(
+            schema.map(|s| s.imports),
+            Ok(vec![
+                "improbable/standard_library.schema".to_owned(),
+                "io/nebulis/base.schema".to_owned()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_schema_commented_header() {
+        let (rest, schema_builder) = schema_file(
+            b"// Copyright
+            // Nebulis
+            package game;
+            // Positions
+            import \"base/position.schema\"; // Position
+            // Health
+            import \"game/health.schema\";
+            // A player
+            type Player {}",
+        )
+        .unwrap();
+        let schema = schema_builder
+            .with_name("player".to_owned())
+            .build()
+            .unwrap();
+        assert_eq!(rest, &b""[..]);
+        assert_eq!(schema.package_name, vec!["game".to_owned()]);
+        assert_eq!(
+            schema.imports,
+            vec![
+                "base/position.schema".to_owned(),
+                "game/health.schema".to_owned()
+            ]
+        );
+        assert_eq!(schema.types[0].comments, vec![" A player".to_owned()]);
+    }
+
     #[test]
     fn test_parse_schema() {
-        let schema_builder = parse_schema(SIMPLE_COMPONENT.as_bytes());
+        let schema_builder = schema_file(SIMPLE_COMPONENT.as_bytes());
         assert!(schema_builder.is_ok());
         let (rest, schema_builder) = schema_builder.unwrap();
         let schema = schema_builder.with_name("test".to_owned()).build();
@@ -130,8 +241,11 @@ mod tests {
             Ok(SchemaFile {
                 package_name: vec!["io".to_owned(), "nebulis".to_owned(), "player".to_owned()],
                 name: "test".to_owned(),
+                path: "test.schema".to_owned(),
+                imports: vec![],
                 types: vec![
                     Type {
+                        annotations: vec![],
                         name: "IsDead".to_owned(),
                         members: vec![],
                         comments: vec![],
@@ -139,8 +253,10 @@ mod tests {
                         enums: vec![]
                     },
                     Type {
+                        annotations: vec![],
                         name: "Damage".to_owned(),
                         members: vec![Member {
+                            annotations: vec![],
                             comments: vec![],
                             name: "points".to_owned(),
                             m_type: DataType::Uint32,
@@ -151,6 +267,7 @@ mod tests {
                         enums: vec![]
                     },
                     Type {
+                        annotations: vec![],
                         name: "DamageResponse".to_owned(),
                         members: vec![],
                         comments: vec![],
@@ -159,20 +276,24 @@ mod tests {
                     },
                 ],
                 enums: vec![Enum {
+                    annotations: vec![],
                     comments: vec![],
                     name: "LifeState".to_owned(),
                     variants: vec![
                         Variant {
+                            annotations: vec![],
                             comments: vec![],
                             name: "ALIVE".to_owned(),
                             id: 0
                         },
                         Variant {
+                            annotations: vec![],
                             comments: vec![],
                             name: "DEAD".to_owned(),
                             id: 1,
                         },
                         Variant {
+                            annotations: vec![],
                             comments: vec![],
                             name: "RESPAWNING".to_owned(),
                             id: 2,
@@ -180,17 +301,21 @@ mod tests {
                     ]
                 }],
                 components: vec![Component {
+                    data: None,
+                    annotations: vec![],
                     id: 601,
                     name: "Health".to_owned(),
                     comments: vec![],
                     members: vec![
                         Member {
+                            annotations: vec![],
                             comments: vec![],
                             name: "hp".to_owned(),
                             id: 1,
                             m_type: DataType::Uint32
                         },
                         Member {
+                            annotations: vec![],
                             comments: vec![],
                             name: "max_hp".to_owned(),
                             id: 2,
@@ -200,25 +325,28 @@ mod tests {
                     events: vec![
                         Event {
                             name: "is_dead".to_owned(),
-                            r_type: DataType::UserDefined(UserDefinedType::Unresolved(
+                            r_type: DataType::UserDefined(UserDefinedType::Unresolved(vec![
                                 "IsDead".to_owned()
-                            ))
+                            ])),
+                            comments: vec![]
                         },
                         Event {
                             name: "took_damage".to_owned(),
-                            r_type: DataType::UserDefined(UserDefinedType::Unresolved(
+                            r_type: DataType::UserDefined(UserDefinedType::Unresolved(vec![
                                 "Damage".to_owned()
-                            ))
+                            ])),
+                            comments: vec![]
                         }
                     ],
                     commands: vec![Command {
                         name: "damage".to_owned(),
-                        r_type: DataType::UserDefined(UserDefinedType::Unresolved(
+                        r_type: DataType::UserDefined(UserDefinedType::Unresolved(vec![
                             "DamageResponse".to_owned()
-                        )),
-                        args: vec![DataType::UserDefined(UserDefinedType::Unresolved(
+                        ])),
+                        args: vec![DataType::UserDefined(UserDefinedType::Unresolved(vec![
                             "Damage".to_owned()
-                        ))],
+                        ]))],
+                        comments: vec![]
                     }],
                     enums: vec![],
                     types: vec![]
@@ -227,4 +355,39 @@ mod tests {
         );
         assert_eq!(rest, &b""[..]);
     }
+
+    #[test]
+    fn test_recover_every_error() {
+        let source = "package game;
+            enum State { ALIVE = 0; DEAD 1; }
+            type Damage {
+                uint32 points 1;
+                uint32 kind = 2;
+            }
+            component Health {
+                id = 1;
+                uint32 hp 1;
+                command Damage hurt Damage);
+                uint32 shield = 3;
+                uint32 max_hp = 4
+            }";
+        let (result, recovered) = recovering(|| schema_file(source.as_bytes()));
+        let (_, builder) = result.unwrap();
+        assert_eq!(
+            recovered
+                .into_iter()
+                .map(|(_, message)| message)
+                .collect::<Vec<_>>(),
+            vec![
+                "expected `=` after enum value name",
+                "expected `=` after field name",
+                "expected `=` after field name",
+                "expected `(` after command name",
+                "expected `;` after field ID",
+            ]
+        );
+        assert_eq!(builder.enums[0].variants.len(), 1);
+        assert_eq!(builder.types[0].members.len(), 1);
+        assert_eq!(builder.components[0].members.len(), 1);
+    }
 }
@@ -1,20 +1,24 @@
 use crate::{
-    ast::{Enum, Member, Type},
+    ast::{Annotation, Enum, Member, Type},
     parser::{
+        annotation::parse_preamble,
         member::parse_member,
         r#enum::parse_enum,
-        utils::{camel_case as parse_type_name, parse_comments, ws0, ws1},
+        recovery::{recover, unexpected},
+        utils::{
+            expect, identifier as parse_type_name, parse_comments, punct, whitespace0, whitespace1,
+            ws0,
+        },
+        IResult,
     },
 };
 
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::char,
     combinator::{map, map_res},
     multi::many0,
-    sequence::{delimited, preceded, terminated, tuple},
-    IResult,
+    sequence::{delimited, pair, preceded, terminated, tuple},
 };
 
 #[derive(Debug)]
@@ -29,6 +33,7 @@ struct TypeBuilder {
     pub name: Option<String>,
     pub members: Vec<Member>,
     pub comments: Vec<String>,
+    pub annotations: Vec<Annotation>,
     pub types: Vec<Type>,
     pub enums: Vec<Enum>,
 }
@@ -53,12 +58,18 @@ impl TypeBuilder {
         self
     }
 
+    pub fn with_annotations(mut self, annotations: Vec<Annotation>) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
     pub fn build(self) -> Result<Type, &'static str> {
         let name = self.name.ok_or("Name could not be found")?;
         Ok(Type {
             name,
             members: self.members,
             comments: self.comments,
+            annotations: self.annotations,
             types: self.types,
             enums: self.enums,
         })
@@ -67,34 +78,57 @@ impl TypeBuilder {
 
 fn parse_property(input: &[u8]) -> IResult<&[u8], TypeProperty> {
     ws0(alt((
-        terminated(ws0(map(parse_member, TypeProperty::Member)), char(';')),
         map(parse_type, TypeProperty::Type),
         map(parse_enum, TypeProperty::Enum),
+        terminated(
+            ws0(map(parse_member, TypeProperty::Member)),
+            expect("expected `;` after field ID", punct(';')),
+        ),
     )))(input)
 }
 
 fn parse_properties(input: &[u8]) -> IResult<&[u8], Vec<TypeProperty>> {
-    many0(ws0(parse_property))(input)
+    map(
+        many0(ws0(recover(alt((
+            parse_property,
+            unexpected("expected `}` or a field"),
+        ))))),
+        |properties| properties.into_iter().flatten().collect(),
+    )(input)
 }
 
 fn parse_type_body(input: &[u8]) -> IResult<&[u8], Vec<TypeProperty>> {
-    delimited(char('{'), ws0(parse_properties), char('}'))(input)
+    delimited(
+        expect("expected `{` after type name", punct('{')),
+        ws0(parse_properties),
+        expect(
+            "expected `}` or a field",
+            preceded(parse_comments, punct('}')),
+        ),
+    )(input)
 }
 
 pub fn parse_type(input: &[u8]) -> IResult<&[u8], Type> {
     map_res(
         map(
             ws0(tuple((
-                parse_comments,
-                preceded(tag("type"), ws1(parse_type_name)),
+                parse_preamble,
+                preceded(
+                    pair(tag("type"), whitespace1),
+                    expect(
+                        "expected type name",
+                        terminated(parse_type_name, whitespace0),
+                    ),
+                ),
                 parse_type_body,
             ))),
-            |(comments, name, properties)| {
+            |((comments, annotations), name, properties)| {
                 properties
                     .into_iter()
                     .fold(TypeBuilder::default(), |acc, val| acc.with_property(val))
                     .with_name(name)
                     .with_comments(comments)
+                    .with_annotations(annotations)
             },
         ),
         |tb| tb.build(),
@@ -104,7 +138,7 @@ pub fn parse_type(input: &[u8]) -> IResult<&[u8], Type> {
 #[cfg(test)]
 mod tests {
 
-    use crate::ast::{UserDefinedType, Variant};
+    use crate::ast::{AnnotationValue, UserDefinedType, Variant};
 
     use super::*;
 
@@ -142,6 +176,85 @@ mod tests {
             Gender gender = 3;
         }";
 
+    const ANNOTATED_TYPE: &str = "
+        // This is used to count animals
+        [RustAttribute(\"derive(Debug)\")]
+        type AnimalCounter {
+            [Range(0, 10)]
+            uint32 rabbits = 1;
+        }";
+
+    #[test]
+    fn test_parse_type_with_closing_comment() {
+        let (rest, ty) =
+            parse_type(b"type Empty {\n  uint32 a = 1; // a\n  // Nothing else\n} // Empty\n")
+                .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(ty.members.len(), 1);
+        assert!(ty.members[0].comments.is_empty());
+    }
+
+    #[test]
+    fn test_parse_annotated_type() {
+        assert_eq!(
+            parse_type(ANNOTATED_TYPE.as_bytes()),
+            Ok((
+                &b""[..],
+                Type {
+                    annotations: vec![Annotation {
+                        name: vec!["RustAttribute".to_owned()],
+                        values: vec![AnnotationValue::String("derive(Debug)".to_owned())]
+                    }],
+                    comments: vec![" This is used to count animals".to_owned()],
+                    name: "AnimalCounter".to_string(),
+                    members: vec![Member {
+                        annotations: vec![Annotation {
+                            name: vec!["Range".to_owned()],
+                            values: vec![
+                                AnnotationValue::Number("0".to_owned()),
+                                AnnotationValue::Number("10".to_owned())
+                            ]
+                        }],
+                        m_type: crate::ast::DataType::Uint32,
+                        name: "rabbits".to_owned(),
+                        id: 1,
+                        comments: vec![]
+                    }],
+                    types: vec![],
+                    enums: vec![]
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_type_commented_after_annotation() {
+        let (_, ty) = parse_type(
+            b"[RustAttribute(\"derive(Debug)\")]
+            // This is used to count animals
+            type AnimalCounter {
+                [Range(0, 10)]
+                // This is used to count rabbits
+                uint32 rabbits = 1;
+            }",
+        )
+        .unwrap();
+        assert_eq!(
+            (
+                ty.comments,
+                ty.annotations.len(),
+                &ty.members[0].comments,
+                ty.members[0].annotations.len()
+            ),
+            (
+                vec![" This is used to count animals".to_owned()],
+                1,
+                &vec![" This is used to count rabbits".to_owned()],
+                1
+            )
+        );
+    }
+
     #[test]
     fn test_parse_type() {
         assert_eq!(
@@ -149,6 +262,7 @@ mod tests {
             Ok((
                 &b""[..],
                 Type {
+                    annotations: vec![],
                     comments: Vec::new(),
                     name: "AnimalCounter".to_string(),
                     members: vec![],
@@ -162,16 +276,19 @@ mod tests {
             Ok((
                 &b""[..],
                 Type {
+                    annotations: vec![],
                     comments: Vec::new(),
                     name: "AnimalCounter".to_string(),
                     members: vec![
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Uint32,
                             name: "rabbits".to_owned(),
                             id: 1,
                             comments: vec![]
                         },
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Double,
                             name: "platypus".to_owned(),
                             id: 2,
@@ -188,16 +305,19 @@ mod tests {
             Ok((
                 &b""[..],
                 Type {
+                    annotations: vec![],
                     comments: vec![" This is used to count animals".to_owned()],
                     name: "AnimalCounter".to_string(),
                     members: vec![
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Uint32,
                             name: "rabbits".to_owned(),
                             id: 1,
                             comments: vec![" This is used to count rabbits".to_owned()]
                         },
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Double,
                             name: "platypus".to_owned(),
                             id: 2,
@@ -214,26 +334,30 @@ mod tests {
             Ok((
                 &b""[..],
                 Type {
+                    annotations: vec![],
                     comments: vec![],
                     name: "Rabbit".to_string(),
                     members: vec![
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::String,
                             name: "name".to_owned(),
                             id: 1,
                             comments: vec![]
                         },
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::UserDefined(UserDefinedType::Unresolved(
-                                "LifeState".to_owned()
+                                vec!["LifeState".to_owned()]
                             )),
                             name: "life_state".to_owned(),
                             id: 2,
                             comments: vec![]
                         },
                         Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::UserDefined(UserDefinedType::Unresolved(
-                                "Gender".to_owned()
+                                vec!["Gender".to_owned()]
                             )),
                             name: "gender".to_owned(),
                             id: 3,
@@ -241,9 +365,11 @@ mod tests {
                         },
                     ],
                     types: vec![Type {
+                        annotations: vec![],
                         comments: vec![],
                         name: "LifeState".to_owned(),
                         members: vec![Member {
+                            annotations: vec![],
                             m_type: crate::ast::DataType::Uint32,
                             name: "health".to_owned(),
                             id: 1,
@@ -253,15 +379,18 @@ mod tests {
                         enums: vec![]
                     }],
                     enums: vec![Enum {
+                        annotations: vec![],
                         comments: vec![],
                         name: "Gender".to_owned(),
                         variants: vec![
                             Variant {
+                                annotations: vec![],
                                 comments: vec![],
                                 name: "MALE".to_owned(),
                                 id: 1
                             },
                             Variant {
+                                annotations: vec![],
                                 comments: vec![],
                                 name: "FEMALE".to_owned(),
                                 id: 2
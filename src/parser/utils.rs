@@ -1,90 +1,84 @@
-use std::{convert::identity, iter::FromIterator};
+use std::iter::FromIterator;
 
 use nom::{
     branch::alt,
-    bytes::complete::{is_not, tag},
-    character::complete::{char, multispace0, multispace1, one_of},
-    combinator::{map, map_opt, map_res, peek, value},
-    error::ParseError,
-    multi::{fold_many1, many0, many1, separated_list1},
-    sequence::{delimited, pair},
-    IResult,
+    bytes::complete::{is_not, tag, take_until},
+    character::complete::{alpha1, alphanumeric1, char, multispace1, one_of, space1},
+    combinator::{cut, eof, map, map_res, opt, peek, recognize, value},
+    error::{context, VerboseError},
+    multi::{many0, many1},
+    sequence::{delimited, pair, preceded, terminated},
 };
 
-pub fn ws0<'a, F: 'a, O, E: ParseError<&'a [u8]>>(
-    inner: F,
-) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>
-where
-    F: FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>,
-{
-    delimited(multispace0, inner, multispace0)
-}
-
-pub fn ws1<'a, F: 'a, O, E: ParseError<&'a [u8]>>(
-    inner: F,
-) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>
-where
-    F: FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>,
-{
-    delimited(multispace1, inner, multispace1)
-}
-
-pub fn uppercase(input: &[u8]) -> IResult<&[u8], char> {
-    one_of("ABCDEFGHIJKLMNOPQRSTUVWXYZ")(input)
-}
+use crate::parser::IResult;
 
-pub fn digit(input: &[u8]) -> IResult<&[u8], char> {
-    one_of("0123456789")(input)
+pub fn block_comment(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    preceded(
+        tag("/*"),
+        expect(
+            "unterminated block comment",
+            terminated(take_until("*/"), tag("*/")),
+        ),
+    )(input)
 }
 
-pub fn lowercase(input: &[u8]) -> IResult<&[u8], char> {
-    one_of("abcdefghijklmnopqrstuvwxyz")(input)
+/// Whitespace and block comments, which are allowed between any two tokens.
+pub fn whitespace0(input: &[u8]) -> IResult<&[u8], ()> {
+    value((), many0(alt((multispace1, block_comment))))(input)
 }
 
-pub fn lower_alphanum(input: &[u8]) -> IResult<&[u8], char> {
-    alt((lowercase, digit))(input)
+pub fn whitespace1(input: &[u8]) -> IResult<&[u8], ()> {
+    value((), many1(alt((multispace1, block_comment))))(input)
 }
 
-pub fn camel_case_component(input: &[u8]) -> IResult<&[u8], String> {
-    map(pair(uppercase, many0(lower_alphanum)), |(c, s)| {
-        c.to_string() + &s.iter().collect::<String>()
-    })(input)
+/// A `//` comment on the same line as the end of a statement. It documents
+/// that statement rather than the next one, so it is skipped.
+pub fn trailing_comment(input: &[u8]) -> IResult<&[u8], ()> {
+    value(
+        (),
+        opt(pair(many0(alt((space1, block_comment))), parse_comment)),
+    )(input)
 }
 
-pub fn camel_case(input: &[u8]) -> IResult<&[u8], String> {
-    fold_many1(camel_case_component, String::new(), |acc, val| acc + &val)(input)
+/// `c` followed by the trailing comment of its line, if any.
+pub fn punct<'a>(c: char) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], char> {
+    terminated(char(c), trailing_comment)
 }
 
-pub fn snake_case_component(input: &[u8]) -> IResult<&[u8], String> {
-    fold_many1(lower_alphanum, String::new(), |acc, val| {
-        acc + &val.to_string()
-    })(input)
+pub fn ws0<'a, F, O>(inner: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>
+where
+    F: 'a + FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    delimited(whitespace0, inner, whitespace0)
 }
 
-pub fn upper_snake_case_component(input: &[u8]) -> IResult<&[u8], String> {
-    fold_many1(uppercase, String::new(), |acc, val| acc + &val.to_string())(input)
+/// Commits to `inner`: once the surrounding construct is recognised, a failure
+/// is reported with `message` instead of backtracking.
+pub fn expect<'a, F, O>(
+    message: &'static str,
+    inner: F,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O, VerboseError<&'a [u8]>>
+where
+    F: FnMut(&'a [u8]) -> IResult<&'a [u8], O, VerboseError<&'a [u8]>>,
+{
+    cut(context(message, inner))
 }
 
-pub fn snake_case(input: &[u8]) -> IResult<&[u8], String> {
-    map_opt(
-        map(separated_list1(char('_'), snake_case_component), |v| {
-            let mut it = v.into_iter();
-            it.next().map(|e| it.fold(e, |acc, val| acc + "_" + &val))
-        }),
-        identity,
-    )(input)
+pub fn digit(input: &[u8]) -> IResult<&[u8], char> {
+    one_of("0123456789")(input)
 }
 
-pub fn upper_snake_case(input: &[u8]) -> IResult<&[u8], String> {
-    map_opt(
-        map(
-            separated_list1(char('_'), upper_snake_case_component),
-            |v| {
-                let mut it = v.into_iter();
-                it.next().map(|e| it.fold(e, |acc, val| acc + "_" + &val))
-            },
+/// A schema identifier, as accepted by the SpatialOS schema lexer.
+pub fn identifier(input: &[u8]) -> IResult<&[u8], String> {
+    map(
+        map_res(
+            recognize(pair(
+                alt((alpha1, tag("_"))),
+                many0(alt((alphanumeric1, tag("_")))),
+            )),
+            std::str::from_utf8,
         ),
-        identity,
+        |s| s.to_string(),
     )(input)
 }
 
@@ -104,6 +98,7 @@ pub fn parse_comment(input: &[u8]) -> IResult<&[u8], Option<String>> {
                     Some,
                 ),
                 value(None, peek(one_of("\n\r"))),
+                value(None, eof),
             )),
         ),
         |(_, c)| c,
@@ -112,10 +107,7 @@ pub fn parse_comment(input: &[u8]) -> IResult<&[u8], Option<String>> {
 
 pub fn parse_comments(input: &[u8]) -> IResult<&[u8], Vec<String>> {
     map(many0(ws0(parse_comment)), |c| {
-        c.into_iter()
-            .filter_map(identity)
-            .map(|s| s.replace("\"", "\\\""))
-            .collect::<Vec<_>>()
+        c.into_iter().flatten().collect::<Vec<_>>()
     })(input)
 }
 
@@ -124,17 +116,39 @@ mod tests {
 
     use super::*;
     #[test]
-    fn test_camel_case_component() {
+    fn test_identifier() {
+        for name in [
+            "Vector3F",
+            "HTTPRequest",
+            "ABTestConfig",
+            "My_Type",
+            "_private",
+            "LEVEL_2",
+        ] {
+            assert_eq!(
+                identifier(name.as_bytes()),
+                Ok((&b""[..], name.to_string()))
+            );
+        }
+        assert_eq!(identifier(b"a.b"), Ok((&b".b"[..], "a".to_string())));
+        assert!(identifier(b"3D").is_err());
+    }
+
+    #[test]
+    fn test_whitespace_with_block_comments() {
         assert_eq!(
-            camel_case_component(b"CamelCase"),
-            Ok((&b"Case"[..], "Camel".to_string()))
-        )
+            whitespace0(b" /* a */\n/* b\n c */ type"),
+            Ok((&b"type"[..], ()))
+        );
+        assert!(whitespace0(b"/* unterminated").is_err());
     }
+
     #[test]
-    fn test_camel_case_component_end() {
+    fn test_trailing_comment() {
         assert_eq!(
-            camel_case_component(b"Camel"),
-            Ok((&b""[..], "Camel".to_string()))
-        )
+            trailing_comment(b" /* a */ // b\n// c"),
+            Ok((&b"\n// c"[..], ()))
+        );
+        assert_eq!(trailing_comment(b"\n// c"), Ok((&b"\n// c"[..], ())));
     }
 }
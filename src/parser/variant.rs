@@ -1,22 +1,33 @@
 use crate::{
     ast::Variant,
-    parser::utils::{parse_comments, parse_u32, upper_snake_case as parse_value_name, ws0},
+    parser::{
+        annotation::parse_preamble,
+        utils::{expect, identifier as parse_value_name, parse_u32, ws0},
+        IResult,
+    },
 };
 use nom::{
     character::complete::char,
     combinator::map,
     sequence::{preceded, tuple},
-    IResult,
 };
 
 pub fn parse_variant(input: &[u8]) -> IResult<&[u8], Variant> {
     map(
         tuple((
-            parse_comments,
+            parse_preamble,
             parse_value_name,
-            preceded(ws0(char('=')), parse_u32),
+            preceded(
+                expect("expected `=` after enum value name", ws0(char('='))),
+                expect("expected enum value after `=`", parse_u32),
+            ),
         )),
-        |(comments, name, id)| Variant { name, id, comments },
+        |((comments, annotations), name, id)| Variant {
+            name,
+            id,
+            comments,
+            annotations,
+        },
     )(input)
 }
 
@@ -32,6 +43,7 @@ mod tests {
             Ok((
                 &b""[..],
                 Variant {
+                    annotations: vec![],
                     comments: Vec::new(),
                     name: "RABBITS_COUNTER".to_string(),
                     id: 1
@@ -43,6 +55,7 @@ mod tests {
             Ok((
                 &b""[..],
                 Variant {
+                    annotations: vec![],
                     comments: vec![" This action is a rabbit counter".to_string()],
                     name: "RABBITS_COUNTER".to_string(),
                     id: 1
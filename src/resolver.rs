@@ -1,211 +1,1211 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-use crate::ast::{
-    ASTNode, Component, DataType, Member, PackageNode, ResolvedTypeKind, SchemaFile, Type,
-    UserDefinedType, AST,
+use crate::{
+    ast::{
+        ASTNode, Component, DataType, Enum, Member, PackageNode, ResolvedTypeKind, SchemaFile,
+        Type, UserDefinedType, AST,
+    },
+    codegen::{nested_module_name, root_path, rust_name},
+    error::Error,
+    validator::{scoped, validate, validate_fields},
 };
 
-type Context = HashMap<String, (String, ResolvedTypeKind)>;
+type Registration = (Vec<String>, (String, ResolvedTypeKind));
+type Exports = HashMap<String, Vec<Registration>>;
+type Definitions = HashMap<String, Type>;
 
-fn register_type<S: AsRef<str>>(path: S, ty: &Type) -> Vec<(String, (String, ResolvedTypeKind))> {
+/// The Rust types used instead of the generated ones.
+struct Overrides<'a> {
+    /// The Rust path of the `EntityId` schema type.
+    entity_id: String,
+    /// The Rust path of the `Entity` schema type.
+    entity: String,
+    /// The Rust paths of the schema types, by qualified schema name.
+    types: &'a BTreeMap<String, String>,
+}
+
+struct Context<'a> {
+    overrides: &'a Overrides<'a>,
+    path: &'a str,
+    package: &'a [String],
+    file: Vec<(&'a str, &'a Registration)>,
+    imports: Vec<(&'a str, &'a Registration)>,
+}
+
+impl<'a> Context<'a> {
+    fn find<I: Iterator<Item = (&'a str, &'a Registration)>>(
+        declarations: I,
+        qualified: &[String],
+    ) -> Vec<(&'a str, &'a Registration)> {
+        declarations
+            .filter(|(_, (name, _))| name.as_slice() == qualified)
+            .collect()
+    }
+
+    /// Looks `path` up from `scope`, from the innermost enclosing type outwards,
+    /// then the package of the schema file and finally the packages enclosing
+    /// it, in the file and everything it imports. Types of other packages are
+    /// only visible through their package-qualified name. Every declaration
+    /// found at the first matching level is returned.
+    fn lookup(&self, scope: &[String], path: &[String]) -> Vec<(&'a str, &'a Registration)> {
+        let qualified = |i: usize| scope[..i].iter().chain(path).cloned().collect::<Vec<_>>();
+        let package = self.package.len();
+        let visible = || self.file.iter().chain(&self.imports).copied();
+        (package..=scope.len())
+            .rev()
+            .map(|i| Self::find(self.file.iter().copied(), &qualified(i)))
+            .chain(
+                (0..=package)
+                    .rev()
+                    .map(|i| Self::find(visible(), &qualified(i))),
+            )
+            .find(|found| !found.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// The declaration `path` refers to, along with its generated Rust type.
+    fn declaration(
+        &self,
+        scope: &[String],
+        declaration: &str,
+        path: &[String],
+    ) -> Result<&'a Registration, Error> {
+        match self.lookup(scope, path).as_slice() {
+            [] => Err(Error::UnresolvedType {
+                file: self.path.to_owned(),
+                declaration: declaration.to_owned(),
+                name: path.join("."),
+            }),
+            [(_, (name, (_, ResolvedTypeKind::Component)))] => Err(Error::InvalidReference {
+                file: self.path.to_owned(),
+                declaration: declaration.to_owned(),
+                message: format!("component {} can't be used as a type", name.join(".")),
+            }),
+            [(_, registration)] => Ok(registration),
+            candidates => Err(Error::AmbiguousType {
+                file: self.path.to_owned(),
+                declaration: declaration.to_owned(),
+                name: path.join("."),
+                candidates: candidates
+                    .iter()
+                    .map(|(file, (name, _))| format!("{} ({})", name.join("."), file))
+                    .collect(),
+            }),
+        }
+    }
+
+    /// The Rust type `path` refers to, overridden or generated.
+    fn resolve(
+        &self,
+        scope: &[String],
+        declaration: &str,
+        path: &[String],
+    ) -> Result<UserDefinedType, Error> {
+        let (name, resolved) = self.declaration(scope, declaration, path)?;
+        Ok(match self.overrides.types.get(&name.join(".")) {
+            Some(rust_type) => UserDefinedType::Resolved(rust_type.clone(), resolved.1.clone()),
+            None => UserDefinedType::from(resolved),
+        })
+    }
+}
+
+/// The path of the module holding the declarations nested in `name`.
+fn nested<S: AsRef<str>>(path: S, name: &str) -> String {
+    path.as_ref().to_owned() + "::" + &nested_module_name(name)
+}
+
+fn register_enum<S: AsRef<str>>(scope: &[String], path: S, en: &Enum) -> Registration {
+    (
+        scoped(scope, &en.name),
+        (
+            path.as_ref().to_string() + "::" + &rust_name(&en.name),
+            ResolvedTypeKind::Enum,
+        ),
+    )
+}
+
+fn register_type<S: AsRef<str>>(scope: &[String], path: S, ty: &Type) -> Vec<Registration> {
+    let scope = scoped(scope, &ty.name);
     let mut types = vec![(
-        ty.name.clone(),
+        scope.clone(),
         (
-            path.as_ref().to_owned() + "::" + &ty.name,
+            path.as_ref().to_owned() + "::" + &rust_name(&ty.name),
             ResolvedTypeKind::Type,
         ),
     )];
+    let path = nested(path, &ty.name);
     types.extend(
         ty.types
             .iter()
-            .map(|ty| register_type(path.as_ref(), ty))
-            .flatten(),
+            .flat_map(|ty| register_type(&scope, &path, ty)),
     );
-    types.extend(ty.enums.iter().map(|en| {
-        (
-            en.name.to_owned(),
-            (
-                path.as_ref().to_string() + "::" + &en.name,
-                ResolvedTypeKind::Enum,
-            ),
-        )
-    }));
+    types.extend(ty.enums.iter().map(|en| register_enum(&scope, &path, en)));
     types
 }
 
 fn register_component<S: AsRef<str>>(
+    scope: &[String],
     path: S,
     comp: &Component,
-) -> Vec<(String, (String, ResolvedTypeKind))> {
+) -> Vec<Registration> {
+    let scope = scoped(scope, &comp.name);
     let mut types = vec![(
-        comp.name.clone(),
+        scope.clone(),
         (
-            path.as_ref().to_owned() + "::" + &comp.name,
+            path.as_ref().to_owned() + "::" + &rust_name(&comp.name),
             ResolvedTypeKind::Component,
         ),
     )];
+    let path = nested(path, &comp.name);
     types.extend(
         comp.types
             .iter()
-            .map(|ty| register_type(path.as_ref(), ty))
-            .flatten(),
+            .flat_map(|ty| register_type(&scope, &path, ty)),
     );
-    types.extend(comp.enums.iter().map(|en| {
-        (
-            en.name.to_owned(),
-            (
-                path.as_ref().to_string() + "::" + &en.name,
-                ResolvedTypeKind::Enum,
-            ),
-        )
-    }));
+    types.extend(comp.enums.iter().map(|en| register_enum(&scope, &path, en)));
     types
 }
 
-fn register_schemas<S: AsRef<str>>(
-    path: S,
-    schema: &SchemaFile,
-) -> Vec<(String, (String, ResolvedTypeKind))> {
+fn register_schemas<S: AsRef<str>>(path: S, schema: &SchemaFile) -> Vec<Registration> {
+    let scope = &schema.package_name;
     let mut types = schema
         .components
         .iter()
-        .map(|comp| register_component(path.as_ref(), comp))
-        .flatten()
+        .flat_map(|comp| register_component(scope, path.as_ref(), comp))
         .collect::<Vec<_>>();
     types.extend(
         schema
             .types
             .iter()
-            .map(|ty| register_type(path.as_ref(), ty))
-            .flatten(),
+            .flat_map(|ty| register_type(scope, path.as_ref(), ty)),
+    );
+    types.extend(
+        schema
+            .enums
+            .iter()
+            .map(|en| register_enum(scope, path.as_ref(), en)),
     );
-    types.extend(schema.enums.iter().map(|en| {
-        (
-            en.name.to_owned(),
-            (
-                path.as_ref().to_string() + "::" + &en.name,
-                ResolvedTypeKind::Enum,
-            ),
-        )
-    }));
     types
 }
 
-fn register_node<S: AsRef<str>>(
-    path: S,
-    node: &ASTNode,
-) -> Vec<(String, (String, ResolvedTypeKind))> {
+fn register_node<S: AsRef<str>>(path: S, node: &ASTNode) -> Vec<(String, Vec<Registration>)> {
     match node {
         ASTNode::PackageNode(package) => package
             .inner
             .iter()
-            .map(|node| register_node(path.as_ref().to_owned() + "::" + &package.name, node))
-            .flatten()
+            .flat_map(|node| {
+                register_node(
+                    path.as_ref().to_owned() + "::" + &rust_name(&package.name),
+                    node,
+                )
+            })
             .collect(),
-        ASTNode::SchemaNode(schema) => {
-            register_schemas(path.as_ref().to_string() + "::" + &schema.name, schema)
+        ASTNode::SchemaNode(schema) => vec![(
+            schema.path.clone(),
+            register_schemas(
+                path.as_ref().to_string() + "::" + &rust_name(&schema.name),
+                schema,
+            ),
+        )],
+    }
+}
+
+fn declarations<'a>(exports: &'a Exports, path: &'a str) -> Vec<(&'a str, &'a Registration)> {
+    exports
+        .get_key_value(path)
+        .map(|(file, types)| types.iter().map(|ty| (file.as_str(), ty)).collect())
+        .unwrap_or_default()
+}
+
+fn schema_context<'a>(
+    overrides: &'a Overrides<'a>,
+    exports: &'a Exports,
+    schema: &'a SchemaFile,
+) -> Result<Context<'a>, Error> {
+    let mut imported = HashSet::new();
+    let mut imports = Vec::new();
+    for import in &schema.imports {
+        if !exports.contains_key(import) {
+            return Err(Error::MissingImport {
+                file: schema.path.clone(),
+                import: import.clone(),
+            });
+        }
+        if *import != schema.path && imported.insert(import) {
+            imports.extend(declarations(exports, import));
         }
     }
+    Ok(Context {
+        overrides,
+        path: &schema.path,
+        package: &schema.package_name,
+        file: declarations(exports, &schema.path),
+        imports,
+    })
 }
 
-fn resolve_date_type(ctx: &Context, data_type: DataType) -> DataType {
-    match data_type {
+fn resolve_date_type(
+    ctx: &Context,
+    scope: &[String],
+    declaration: &str,
+    data_type: DataType,
+) -> Result<DataType, Error> {
+    Ok(match data_type {
         DataType::UserDefined(UserDefinedType::Unresolved(unresolved)) => {
-            DataType::UserDefined(UserDefinedType::from(
-                ctx.get(&unresolved)
-                    .unwrap_or_else(|| panic!("Unable to resolve: {}", unresolved)),
-            ))
+            DataType::UserDefined(ctx.resolve(scope, declaration, &unresolved)?)
         }
+        DataType::EntityID => DataType::UserDefined(UserDefinedType::Resolved(
+            ctx.overrides.entity_id.clone(),
+            ResolvedTypeKind::EntityId,
+        )),
+        DataType::Entity => DataType::UserDefined(UserDefinedType::Resolved(
+            ctx.overrides.entity.clone(),
+            ResolvedTypeKind::Entity,
+        )),
         DataType::Map(ty1, ty2) => DataType::Map(
-            Box::new(resolve_date_type(ctx, *ty1)),
-            Box::new(resolve_date_type(ctx, *ty2)),
+            Box::new(resolve_date_type(ctx, scope, declaration, *ty1)?),
+            Box::new(resolve_date_type(ctx, scope, declaration, *ty2)?),
         ),
-        DataType::List(ty) => DataType::List(Box::new(resolve_date_type(ctx, *ty))),
-        DataType::Option(ty) => DataType::Option(Box::new(resolve_date_type(ctx, *ty))),
+        DataType::List(ty) => {
+            DataType::List(Box::new(resolve_date_type(ctx, scope, declaration, *ty)?))
+        }
+        DataType::Option(ty) => {
+            DataType::Option(Box::new(resolve_date_type(ctx, scope, declaration, *ty)?))
+        }
         _ => data_type,
-    }
+    })
 }
 
-fn resolve_member(ctx: &Context, mut member: Member) -> Member {
-    member.m_type = resolve_date_type(ctx, member.m_type);
-    member
+fn resolve_member(ctx: &Context, scope: &[String], mut member: Member) -> Result<Member, Error> {
+    let declaration = scoped(scope, &member.name).join(".");
+    member.m_type = resolve_date_type(ctx, scope, &declaration, member.m_type)?;
+    Ok(member)
 }
 
-fn resolve_component(ctx: &Context, mut comp: Component) -> Component {
+fn resolve_component(
+    ctx: &Context,
+    scope: &[String],
+    mut comp: Component,
+) -> Result<Component, Error> {
+    let scope = &scoped(scope, &comp.name);
+    let declaration = |name: &str| scoped(scope, name).join(".");
+    comp.data = comp
+        .data
+        .map(|data| match data {
+            // The fields of the data type are inlined, so it refers to the
+            // generated type even when overridden.
+            DataType::UserDefined(UserDefinedType::Unresolved(path)) => ctx
+                .declaration(scope, &declaration("data"), &path)
+                .map(|(_, resolved)| DataType::UserDefined(UserDefinedType::from(resolved))),
+            data => resolve_date_type(ctx, scope, &declaration("data"), data),
+        })
+        .transpose()?;
     comp.members = comp
         .members
         .into_iter()
-        .map(|member| resolve_member(ctx, member))
-        .collect();
+        .map(|member| resolve_member(ctx, scope, member))
+        .collect::<Result<_, _>>()?;
     comp.types = comp
         .types
         .into_iter()
-        .map(|ty| resolve_type(ctx, ty))
-        .collect();
-    comp.commands = comp.commands.into_iter().map(|mut com| {
-        com.args = com.args.into_iter().map(|arg| resolve_date_type(ctx, arg)).collect();
-        com.r_type = resolve_date_type(ctx, com.r_type);
-        com
-    }).collect();
-    comp
+        .map(|ty| resolve_type(ctx, scope, ty))
+        .collect::<Result<_, _>>()?;
+    comp.commands = comp
+        .commands
+        .into_iter()
+        .map(|mut com| {
+            let declaration = declaration(&com.name);
+            com.args = com
+                .args
+                .into_iter()
+                .map(|arg| resolve_date_type(ctx, scope, &declaration, arg))
+                .collect::<Result<_, _>>()?;
+            com.r_type = resolve_date_type(ctx, scope, &declaration, com.r_type)?;
+            Ok(com)
+        })
+        .collect::<Result<_, _>>()?;
+    comp.events = comp
+        .events
+        .into_iter()
+        .map(|mut event| {
+            event.r_type = resolve_date_type(ctx, scope, &declaration(&event.name), event.r_type)?;
+            Ok(event)
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(comp)
 }
 
-fn resolve_type(ctx: &Context, mut ty: Type) -> Type {
+fn resolve_type(ctx: &Context, scope: &[String], mut ty: Type) -> Result<Type, Error> {
+    let scope = &scoped(scope, &ty.name);
     ty.members = ty
         .members
         .into_iter()
-        .map(|member| resolve_member(ctx, member))
-        .collect();
+        .map(|member| resolve_member(ctx, scope, member))
+        .collect::<Result<_, _>>()?;
     ty.types = ty
         .types
         .into_iter()
-        .map(|ty| resolve_type(ctx, ty))
-        .collect();
-    ty
+        .map(|ty| resolve_type(ctx, scope, ty))
+        .collect::<Result<_, _>>()?;
+    Ok(ty)
 }
 
-fn resolve_schema(ctx: &Context, mut schema: SchemaFile) -> SchemaFile {
-    schema.components = schema
-        .components
+fn resolve_schema(
+    overrides: &Overrides,
+    exports: &Exports,
+    mut schema: SchemaFile,
+) -> Result<SchemaFile, Error> {
+    let components = std::mem::take(&mut schema.components);
+    let types = std::mem::take(&mut schema.types);
+    let ctx = &schema_context(overrides, exports, &schema)?;
+    let scope = &schema.package_name;
+    let components = components
         .into_iter()
-        .map(|component| resolve_component(ctx, component))
-        .collect();
-    schema.types = schema
-        .types
+        .map(|component| resolve_component(ctx, scope, component))
+        .collect::<Result<_, _>>()?;
+    let types = types
         .into_iter()
-        .map(|t| resolve_type(ctx, t))
-        .collect();
-    schema
+        .map(|t| resolve_type(ctx, scope, t))
+        .collect::<Result<_, _>>()?;
+    schema.components = components;
+    schema.types = types;
+    Ok(schema)
 }
 
-fn resolve_package(ctx: &Context, mut package: PackageNode) -> PackageNode {
+fn resolve_package(
+    overrides: &Overrides,
+    exports: &Exports,
+    mut package: PackageNode,
+) -> Result<PackageNode, Error> {
     package.inner = package
         .inner
         .into_iter()
-        .map(|n| resolve_node(ctx, n))
-        .collect();
-    package
+        .map(|n| resolve_node(overrides, exports, n))
+        .collect::<Result<_, _>>()?;
+    Ok(package)
+}
+
+fn resolve_node(overrides: &Overrides, exports: &Exports, node: ASTNode) -> Result<ASTNode, Error> {
+    Ok(match node {
+        ASTNode::PackageNode(package) => {
+            ASTNode::PackageNode(resolve_package(overrides, exports, package)?)
+        }
+        ASTNode::SchemaNode(schema) => {
+            ASTNode::SchemaNode(resolve_schema(overrides, exports, schema)?)
+        }
+    })
 }
 
-fn resolve_node(ctx: &Context, node: ASTNode) -> ASTNode {
+fn define_types<S: AsRef<str>>(path: S, types: &[Type]) -> Vec<(String, Type)> {
+    types
+        .iter()
+        .flat_map(|ty| {
+            let mut definitions = vec![(
+                path.as_ref().to_owned() + "::" + &rust_name(&ty.name),
+                ty.clone(),
+            )];
+            definitions.extend(define_types(nested(path.as_ref(), &ty.name), &ty.types));
+            definitions
+        })
+        .collect()
+}
+
+fn define_node<S: AsRef<str>>(path: S, node: &ASTNode) -> Vec<(String, Type)> {
     match node {
-        ASTNode::PackageNode(package) => ASTNode::PackageNode(resolve_package(ctx, package)),
-        ASTNode::SchemaNode(schema) => ASTNode::SchemaNode(resolve_schema(ctx, schema)),
+        ASTNode::PackageNode(package) => package
+            .inner
+            .iter()
+            .flat_map(|node| {
+                define_node(
+                    path.as_ref().to_owned() + "::" + &rust_name(&package.name),
+                    node,
+                )
+            })
+            .collect(),
+        ASTNode::SchemaNode(schema) => {
+            let path = path.as_ref().to_string() + "::" + &rust_name(&schema.name);
+            schema
+                .components
+                .iter()
+                .flat_map(|comp| define_types(nested(&path, &comp.name), &comp.types))
+                .chain(define_types(&path, &schema.types))
+                .collect()
+        }
+    }
+}
+
+/// Copies the fields of the type referenced by `data TypeName;` into the
+/// component, keeping their field IDs, which must not clash with the fields
+/// the component declares itself. The `data` is then cleared, so resolving
+/// the AST again doesn't copy them twice.
+fn inline_component_data(
+    definitions: &Definitions,
+    file: &str,
+    scope: &[String],
+    mut comp: Component,
+) -> Result<Component, Error> {
+    let declaration = scoped(scope, &comp.name).join(".") + ".data";
+    let invalid = |message: String| Error::InvalidReference {
+        file: file.to_owned(),
+        declaration,
+        message,
+    };
+    match comp.data.take() {
+        None => {}
+        Some(DataType::UserDefined(UserDefinedType::Resolved(path, ResolvedTypeKind::Type))) => {
+            let ty = definitions
+                .get(&path)
+                .ok_or_else(|| invalid(format!("unable to find the definition of {}", path)))?;
+            comp.members.extend(ty.members.iter().cloned());
+            let errors = validate_fields(file, &scoped(scope, &comp.name), &comp);
+            if !errors.is_empty() {
+                return Err(Error::multiple(errors));
+            }
+        }
+        Some(data) => {
+            return Err(invalid(format!(
+                "data of a component must be a type, found {}",
+                data.rust_type()
+            )))
+        }
+    }
+    Ok(comp)
+}
+
+fn inline_node(definitions: &Definitions, node: ASTNode) -> Result<ASTNode, Error> {
+    Ok(match node {
+        ASTNode::PackageNode(mut package) => {
+            package.inner = package
+                .inner
+                .into_iter()
+                .map(|n| inline_node(definitions, n))
+                .collect::<Result<_, _>>()?;
+            ASTNode::PackageNode(package)
+        }
+        ASTNode::SchemaNode(mut schema) => {
+            let components = std::mem::take(&mut schema.components);
+            schema.components = components
+                .into_iter()
+                .map(|comp| {
+                    inline_component_data(definitions, &schema.path, &schema.package_name, comp)
+                })
+                .collect::<Result<_, _>>()?;
+            ASTNode::SchemaNode(schema)
+        }
+    })
+}
+
+/// The types a field stores inline, and whether each is behind an `option`.
+/// `list` and `map` are heap allocated so they never make a type recursive.
+fn inline_references(data_type: &DataType, optional: bool) -> Vec<(&str, bool)> {
+    match data_type {
+        DataType::UserDefined(UserDefinedType::Resolved(path, ResolvedTypeKind::Type)) => {
+            vec![(path.as_str(), optional)]
+        }
+        DataType::Option(inner) => inline_references(inner, true),
+        _ => vec![],
     }
 }
 
-pub fn resolve_types<S: AsRef<str>>(mut ast: AST, module: S) -> AST {
-    let ctx = ast
+/// Whether a value of the type at `from` stores a value of the type at `to`
+/// inline, following only plain fields when `direct` is set.
+fn reaches<'a>(definitions: &'a Definitions, from: &'a str, to: &str, direct: bool) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![from];
+    while let Some(path) = stack.pop() {
+        if path == to {
+            return true;
+        }
+        if !visited.insert(path) {
+            continue;
+        }
+        if let Some(ty) = definitions.get(path) {
+            stack.extend(
+                ty.members
+                    .iter()
+                    .flat_map(|m| inline_references(&m.m_type, false))
+                    .filter(|(_, optional)| !direct || !optional)
+                    .map(|(path, _)| path),
+            );
+        }
+    }
+    false
+}
+
+/// Boxes the `option` fields through which the type at `owner` contains
+/// itself, and rejects the plain fields that do, as no indirection could be
+/// added without changing the schema.
+fn box_type(
+    definitions: &Definitions,
+    file: &str,
+    scope: &[String],
+    path: &str,
+    mut ty: Type,
+) -> Result<Type, Error> {
+    let scope = &scoped(scope, &ty.name);
+    let owner = path.to_owned() + "::" + &rust_name(&ty.name);
+    let path = &nested(path, &ty.name);
+    ty.members = ty
+        .members
+        .into_iter()
+        .map(|mut member| {
+            member.m_type = match member.m_type {
+                DataType::UserDefined(UserDefinedType::Resolved(
+                    target,
+                    ResolvedTypeKind::Type,
+                )) if reaches(definitions, &target, &owner, true) => {
+                    return Err(Error::InvalidReference {
+                        file: file.to_owned(),
+                        declaration: scoped(scope, &member.name).join("."),
+                        message: format!(
+                            "{} contains itself, break the cycle with an `option`, `list` or `map`",
+                            scope.join(".")
+                        ),
+                    })
+                }
+                DataType::Option(inner) => match *inner {
+                    DataType::UserDefined(UserDefinedType::Resolved(ref target, _))
+                        if reaches(definitions, target, &owner, false) =>
+                    {
+                        DataType::Option(Box::new(DataType::Boxed(inner)))
+                    }
+                    _ => DataType::Option(inner),
+                },
+                data_type => data_type,
+            };
+            Ok(member)
+        })
+        .collect::<Result<_, _>>()?;
+    ty.types = ty
+        .types
+        .into_iter()
+        .map(|ty| box_type(definitions, file, scope, path, ty))
+        .collect::<Result<_, _>>()?;
+    Ok(ty)
+}
+
+fn box_node<S: AsRef<str>>(
+    definitions: &Definitions,
+    path: S,
+    node: ASTNode,
+) -> Result<ASTNode, Error> {
+    Ok(match node {
+        ASTNode::PackageNode(mut package) => {
+            let path = path.as_ref().to_owned() + "::" + &rust_name(&package.name);
+            package.inner = package
+                .inner
+                .into_iter()
+                .map(|n| box_node(definitions, &path, n))
+                .collect::<Result<_, _>>()?;
+            ASTNode::PackageNode(package)
+        }
+        ASTNode::SchemaNode(mut schema) => {
+            let path = path.as_ref().to_string() + "::" + &rust_name(&schema.name);
+            let components = std::mem::take(&mut schema.components);
+            let types = std::mem::take(&mut schema.types);
+            let (file, scope) = (&schema.path, &schema.package_name);
+            let box_types = |types: Vec<Type>, scope: &[String], path: &str| {
+                types
+                    .into_iter()
+                    .map(|ty| box_type(definitions, file, scope, path, ty))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            let components = components
+                .into_iter()
+                .map(|mut comp| {
+                    let scope = scoped(scope, &comp.name);
+                    comp.types = box_types(comp.types, &scope, &nested(&path, &comp.name))?;
+                    Ok(comp)
+                })
+                .collect::<Result<_, _>>()?;
+            let types = box_types(types, scope, &path)?;
+            schema.components = components;
+            schema.types = types;
+            ASTNode::SchemaNode(schema)
+        }
+    })
+}
+
+/// Validates the AST (see `validate`), then resolves every type reference to
+/// the path of its generated Rust type, for code generated at the module path
+/// `module` (see `AST::generate`).
+pub fn resolve_types<S: AsRef<str>>(ast: AST, module: S) -> Result<AST, Error> {
+    validate(&ast)?;
+    resolve_types_with_overrides(ast, module.as_ref(), &BTreeMap::new())
+}
+
+/// Same as `resolve_types`, referring to the schema types named in
+/// `overrides`, like `improbable.Coordinates`, by the Rust type they map to.
+/// `EntityId` and `Entity` override the root types of the same name.
+pub(crate) fn resolve_types_with_overrides(
+    mut ast: AST,
+    module: &str,
+    overrides: &BTreeMap<String, String>,
+) -> Result<AST, Error> {
+    let root = root_path(module)?;
+    let exports = ast
         .inner
         .iter()
-        .map(|node| register_node("crate::".to_string() + module.as_ref(), node))
-        .flatten()
-        .collect::<HashMap<_, _>>();
+        .flat_map(|node| register_node(&root, node))
+        .collect::<Exports>();
+
+    let mut unused = overrides
+        .keys()
+        .filter(|name| !["EntityId", "Entity"].contains(&name.as_str()))
+        .collect::<BTreeSet<_>>();
+    for (name, _) in exports.values().flatten() {
+        unused.remove(&name.join("."));
+    }
+    if let Some(name) = unused.into_iter().next() {
+        return Err(Error::Config {
+            message: format!("type override `{}` doesn't match any schema type", name),
+        });
+    }
+
+    let root_type = |name: &str| {
+        overrides
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| format!("{}::{}", root, name))
+    };
+    let overrides = Overrides {
+        entity_id: root_type("EntityId"),
+        entity: root_type("Entity"),
+        types: overrides,
+    };
+
+    ast.inner = ast
+        .inner
+        .into_iter()
+        .map(|node| resolve_node(&overrides, &exports, node))
+        .collect::<Result<_, _>>()?;
+
+    let definitions = ast
+        .inner
+        .iter()
+        .flat_map(|node| define_node(&root, node))
+        .collect::<Definitions>();
+
+    ast.inner = ast
+        .inner
+        .into_iter()
+        .map(|node| inline_node(&definitions, node))
+        .collect::<Result<_, _>>()?;
 
     ast.inner = ast
         .inner
         .into_iter()
-        .map(|node| resolve_node(&ctx, node))
-        .collect();
-    ast
+        .map(|node| box_node(&definitions, &root, node))
+        .collect::<Result<_, _>>()?;
+    Ok(ast)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::schema_file::parse_schema;
+
+    fn schema(path: &str, source: &str) -> SchemaFile {
+        let name = path.trim_end_matches(".schema").rsplit('/').next().unwrap();
+        parse_schema(path, source.as_bytes())
+            .unwrap()
+            .with_name(name.to_owned())
+            .with_path(path.to_owned())
+            .build()
+            .unwrap()
+    }
+
+    fn ast(schemas: &[SchemaFile]) -> AST {
+        AST {
+            inner: schemas
+                .iter()
+                .map(|s| ASTNode::package_schema(s, &s.package_name))
+                .collect(),
+        }
+    }
+
+    fn members(ast: &AST) -> Vec<DataType> {
+        fn collect(node: &ASTNode, data: &mut Vec<DataType>) {
+            match node {
+                ASTNode::PackageNode(pn) => pn.inner.iter().for_each(|n| collect(n, data)),
+                ASTNode::SchemaNode(schema) => data.extend(
+                    schema
+                        .components
+                        .iter()
+                        .flat_map(|c| c.members.iter().map(|m| m.m_type.clone())),
+                ),
+            }
+        }
+        let mut data = Vec::new();
+        ast.inner.iter().for_each(|n| collect(n, &mut data));
+        data
+    }
+
+    const BASE: &str = "
+        package base;
+        type Coordinates { double x = 1; }
+    ";
+
+    #[test]
+    fn test_resolve_imported_type() {
+        let ast = ast(&[
+            schema("base/base.schema", BASE),
+            schema(
+                "game/position.schema",
+                "package game;
+                import \"base/base.schema\";
+                component Position { id = 1; base.Coordinates coords = 1; }",
+            ),
+        ]);
+        assert_eq!(
+            members(&resolve_types(ast, "generated").unwrap()),
+            vec![DataType::UserDefined(UserDefinedType::Resolved(
+                "crate::generated::base::base::Coordinates".to_owned(),
+                ResolvedTypeKind::Type
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_resolve_overridden_type() {
+        let ast = || {
+            ast(&[
+                schema("base/base.schema", BASE),
+                schema(
+                    "game/position.schema",
+                    "package game;
+                    import \"base/base.schema\";
+                    component Position { id = 1; base.Coordinates coords = 1; }",
+                ),
+            ])
+        };
+        let overrides = |name: &str| {
+            Some((name.to_owned(), "glam::DVec3".to_owned()))
+                .into_iter()
+                .collect()
+        };
+        assert_eq!(
+            members(
+                &resolve_types_with_overrides(ast(), "generated", &overrides("base.Coordinates"))
+                    .unwrap()
+            ),
+            vec![DataType::UserDefined(UserDefinedType::Resolved(
+                "glam::DVec3".to_owned(),
+                ResolvedTypeKind::Type
+            ))]
+        );
+        assert_eq!(
+            resolve_types_with_overrides(ast(), "generated", &overrides("base.Vector"))
+                .unwrap_err()
+                .to_string(),
+            "type override `base.Vector` doesn't match any schema type"
+        );
+    }
+
+    #[test]
+    fn test_resolve_root_types() {
+        let ast = || {
+            ast(&[schema(
+                "game/owner.schema",
+                "package game;
+                component Owner { id = 1; EntityId owner = 1; list<Entity> spawned = 2; }",
+            )])
+        };
+        let resolved = |path: &str, kind| {
+            DataType::UserDefined(UserDefinedType::Resolved(path.to_owned(), kind))
+        };
+        let data = members(&resolve_types(ast(), "crate::generated").unwrap());
+        assert_eq!(
+            data,
+            vec![
+                resolved("crate::generated::EntityId", ResolvedTypeKind::EntityId),
+                DataType::List(Box::new(resolved(
+                    "crate::generated::Entity",
+                    ResolvedTypeKind::Entity
+                ))),
+            ]
+        );
+        assert_eq!(
+            data.iter()
+                .map(|d| d.spatial_type().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["EntityId", "list<Entity>"]
+        );
+        let overrides = Some(("EntityId".to_owned(), "worker::EntityId".to_owned()))
+            .into_iter()
+            .collect();
+        assert_eq!(
+            members(&resolve_types_with_overrides(ast(), "crate::generated", &overrides).unwrap())
+                [0],
+            resolved("worker::EntityId", ResolvedTypeKind::EntityId)
+        );
+    }
+
+    #[test]
+    fn test_resolve_overridden_component_data() {
+        let ast = ast(&[schema(
+            "g/p.schema",
+            "package g;
+            type Coords { double x = 1; }
+            component P { id = 1; data Coords; }
+            component Q { id = 2; Coords coords = 1; }",
+        )]);
+        let overrides = Some(("g.Coords".to_owned(), "crate::math::Coords".to_owned()))
+            .into_iter()
+            .collect();
+        assert_eq!(
+            members(&resolve_types_with_overrides(ast, "generated", &overrides).unwrap()),
+            vec![
+                DataType::Double,
+                DataType::UserDefined(UserDefinedType::Resolved(
+                    "crate::math::Coords".to_owned(),
+                    ResolvedTypeKind::Type
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_type_in_keyword_package() {
+        let ast = ast(&[
+            schema(
+                "improbable/type/self.schema",
+                "package improbable.type;
+                type match { double x = 1; }",
+            ),
+            schema(
+                "game/position.schema",
+                "package game;
+                import \"improbable/type/self.schema\";
+                component Position { id = 1; improbable.type.match coords = 1; }",
+            ),
+        ]);
+        assert_eq!(
+            members(&resolve_types(ast, "generated").unwrap()),
+            vec![DataType::UserDefined(UserDefinedType::Resolved(
+                "crate::generated::improbable::r#type::self_::r#match".to_owned(),
+                ResolvedTypeKind::Type
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_resolve_qualified_type() {
+        let ast = ast(&[
+            schema(
+                "improbable/interest.schema",
+                "package improbable;
+                type ComponentInterest {
+                    type Query { uint32 id = 1; }
+                    list<Query> queries = 1;
+                }
+                component Interest { id = 58; ComponentInterest.Query query = 1; }",
+            ),
+            schema(
+                "game/query.schema",
+                "package game;
+                import \"improbable/interest.schema\";
+                component LastQuery { id = 1; improbable.ComponentInterest.Query query = 1; }",
+            ),
+        ]);
+        let query = DataType::UserDefined(UserDefinedType::Resolved(
+            "crate::generated::improbable::interest::component_interest::Query".to_owned(),
+            ResolvedTypeKind::Type,
+        ));
+        assert_eq!(
+            members(&resolve_types(ast, "generated").unwrap()),
+            vec![query.clone(), query]
+        );
+    }
+
+    #[test]
+    fn test_resolve_innermost_scope_first() {
+        let ast = ast(&[
+            schema(
+                "base/empty.schema",
+                "package base;
+                type Empty {}",
+            ),
+            schema(
+                "game/query.schema",
+                "package game;
+                import \"base/empty.schema\";
+                type Empty {}
+                type Query {}
+                type ComponentInterest {
+                    type Query {}
+                }
+                component Interest {
+                    id = 1;
+                    Query top_level = 1;
+                    ComponentInterest.Query nested = 2;
+                    Empty empty = 3;
+                    base.Empty base_empty = 4;
+                }",
+            ),
+        ]);
+        let resolved = |path: &str| {
+            DataType::UserDefined(UserDefinedType::Resolved(
+                path.to_owned(),
+                ResolvedTypeKind::Type,
+            ))
+        };
+        assert_eq!(
+            members(&resolve_types(ast, "generated").unwrap()),
+            vec![
+                resolved("crate::generated::game::query::Query"),
+                resolved("crate::generated::game::query::component_interest::Query"),
+                resolved("crate::generated::game::query::Empty"),
+                resolved("crate::generated::base::empty::Empty"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_ambiguous_type() {
+        let ast = ast(&[
+            schema("a/empty.schema", "package a; type Empty {}"),
+            schema("a/other.schema", "package a; type Empty {}"),
+            schema(
+                "game/login.schema",
+                "package game;
+                import \"a/empty.schema\";
+                import \"a/other.schema\";
+                component Login { id = 1; a.Empty empty = 1; }",
+            ),
+        ]);
+        // `validate` rejects the duplicate first, so skip it.
+        assert_eq!(
+            resolve_types_with_overrides(ast, "generated", &BTreeMap::new())
+                .unwrap_err()
+                .to_string(),
+            "game/login.schema: game.Login.empty: ambiguous reference to a.Empty, \
+            could be a.Empty (a/empty.schema), a.Empty (a/other.schema)"
+        );
+    }
+
+    #[test]
+    fn test_resolve_validates_first() {
+        let ast = ast(&[
+            schema("a/empty.schema", "package a; type Empty {}"),
+            schema("a/other.schema", "package a; type Empty {}"),
+        ]);
+        assert_eq!(
+            resolve_types(ast, "generated").unwrap_err().to_string(),
+            "a/other.schema: a.Empty: type `Empty` is already declared in package `a` \
+            (first declared as a.Empty in a/empty.schema)"
+        );
+    }
+
+    #[test]
+    fn test_nested_type_not_visible_from_sibling() {
+        let ast = ast(&[schema(
+            "g/g.schema",
+            "package g; type Outer { type Inner {} } component C { id = 1; Inner i = 1; }",
+        )]);
+        assert_eq!(
+            resolve_types(ast, "generated").unwrap_err().to_string(),
+            "g/g.schema: g.C.i: unable to resolve Inner"
+        );
+    }
+
+    #[test]
+    fn test_unqualified_type_of_other_package() {
+        let ast = ast(&[
+            schema("other/shared.schema", "package other; type Empty {}"),
+            schema(
+                "game/thing.schema",
+                "package game;
+                import \"other/shared.schema\";
+                component Thing { id = 1; Empty empty = 1; }",
+            ),
+        ]);
+        assert_eq!(
+            resolve_types(ast, "generated").unwrap_err().to_string(),
+            "game/thing.schema: game.Thing.empty: unable to resolve Empty"
+        );
+    }
+
+    fn type_members(ast: &AST) -> Vec<(String, String)> {
+        fn collect(ty: &Type, data: &mut Vec<(String, String)>) {
+            data.extend(
+                ty.members
+                    .iter()
+                    .map(|m| (m.name.clone(), m.m_type.rust_type())),
+            );
+            ty.types.iter().for_each(|ty| collect(ty, data));
+        }
+        let mut data = Vec::new();
+        for node in &ast.inner {
+            if let ASTNode::PackageNode(package) = node {
+                for node in &package.inner {
+                    if let ASTNode::SchemaNode(schema) = node {
+                        schema.types.iter().for_each(|ty| collect(ty, &mut data));
+                    }
+                }
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_box_recursive_types() {
+        let ast = ast(&[schema(
+            "game/tree.schema",
+            "package game;
+            type Node {
+                option<Node> next = 1;
+                list<Node> children = 2;
+                Leaf leaf = 3;
+                type Leaf { option<Node> parent = 1; option<Leaf> sibling = 2; }
+            }
+            type Wrapper { option<Node> node = 1; }",
+        )]);
+        let node = "crate::generated::game::tree::Node";
+        let leaf = "crate::generated::game::tree::node::Leaf";
+        assert_eq!(
+            type_members(&resolve_types(ast, "generated").unwrap()),
+            vec![
+                ("next".to_owned(), format!("Option<Box<{}>>", node)),
+                ("children".to_owned(), format!("Vec<{}>", node)),
+                ("leaf".to_owned(), leaf.to_owned()),
+                ("parent".to_owned(), format!("Option<Box<{}>>", node)),
+                ("sibling".to_owned(), format!("Option<Box<{}>>", leaf)),
+                ("node".to_owned(), format!("Option<{}>", node)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reject_type_containing_itself() {
+        let resolve = |source| resolve_types(ast(&[schema("game/loop.schema", source)]), "g");
+        assert_eq!(
+            resolve("package game; type Loop { Loop inner = 1; }")
+                .unwrap_err()
+                .to_string(),
+            "game/loop.schema: game.Loop.inner: game.Loop contains itself, \
+             break the cycle with an `option`, `list` or `map`"
+        );
+        assert_eq!(
+            resolve("package game; type A { B b = 1; } type B { option<uint32> x = 1; A a = 2; }")
+                .unwrap_err()
+                .to_string(),
+            "game/loop.schema: game.A.b: game.A contains itself, \
+             break the cycle with an `option`, `list` or `map`"
+        );
+        assert!(resolve("package game; type A { B b = 1; } type B { option<A> a = 1; }").is_ok());
+    }
+
+    #[test]
+    fn test_resolve_component_data() {
+        let ast = resolve_types(
+            ast(&[
+                schema("base/base.schema", BASE),
+                schema(
+                    "game/position.schema",
+                    "package game;
+                    import \"base/base.schema\";
+                    component Position { id = 1; data base.Coordinates; }",
+                ),
+            ]),
+            "generated",
+        )
+        .unwrap();
+        assert_eq!(members(&ast), vec![DataType::Double]);
+    }
+
+    #[test]
+    fn test_reject_component_data_clashing_with_fields() {
+        let field_clash = ast(&[schema(
+            "game/c.schema",
+            "package game;
+            type D { uint32 a = 1; uint32 b = 2; }
+            component C { id = 1; data D; uint32 b = 1; }",
+        )]);
+        assert_eq!(
+            resolve_types(field_clash, "generated")
+                .unwrap_err()
+                .to_string(),
+            "game/c.schema: game.C.a: field ID 1 is already used \
+             (first declared as game.C.b in game/c.schema)\n\n\
+             game/c.schema: game.C.b: field `b` is already declared \
+             (first declared as game.C.b in game/c.schema)"
+        );
+        let event_clash = ast(&[schema(
+            "game/c.schema",
+            "package game;
+            type D { uint32 a = 1; }
+            component C { id = 1; data D; event D a; }",
+        )]);
+        assert_eq!(
+            resolve_types(event_clash, "generated")
+                .unwrap_err()
+                .to_string(),
+            "game/c.schema: game.C.a: field `a` is already declared \
+             (first declared as game.C.a in game/c.schema)"
+        );
+    }
+
+    #[test]
+    fn test_resolve_twice() {
+        let ast = ast(&[schema(
+            "game/shared.schema",
+            "package game;
+            type Node { list<Node> children = 1; option<Node> parent = 2; }
+            component Shared { id = 1; data Node; }",
+        )]);
+        let resolved = resolve_types(ast, "generated").unwrap();
+        let once = format!("{:?}", resolved);
+        let twice = resolve_types(resolved, "generated").unwrap();
+        assert_eq!(format!("{:?}", twice), once);
+        assert!(twice.generate_string("generated").is_ok());
+    }
+
+    #[test]
+    fn test_resolve_primitive_component_data() {
+        let ast = ast(&[schema(
+            "game/position.schema",
+            "package game;
+            component Position { id = 1; data double; }",
+        )]);
+        assert_eq!(
+            resolve_types(ast, "generated").unwrap_err().to_string(),
+            "game/position.schema: game.Position.data: data of a component must be a type, found f64"
+        );
+    }
+
+    #[test]
+    fn test_resolve_not_imported_type() {
+        let ast = ast(&[
+            schema("base/base.schema", BASE),
+            schema(
+                "game/position.schema",
+                "package game;
+                component Position { id = 1; Coordinates coords = 1; }",
+            ),
+        ]);
+        assert!(matches!(
+            resolve_types(ast, "generated"),
+            Err(Error::UnresolvedType { file, declaration, name })
+                if file == "game/position.schema"
+                    && declaration == "game.Position.coords"
+                    && name == "Coordinates"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_missing_import() {
+        let ast = ast(&[schema(
+            "game/position.schema",
+            "package game;
+            import \"base/missing.schema\";
+            component Position { id = 1; }",
+        )]);
+        assert!(matches!(
+            resolve_types(ast, "generated"),
+            Err(Error::MissingImport { file, import })
+                if file == "game/position.schema" && import == "base/missing.schema"
+        ));
+    }
 }
@@ -0,0 +1,539 @@
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    ast::{Component, Enum, Member, SchemaFile, Type, AST},
+    codegen::{nested_module_name, rust_name, to_upper_camel_case},
+    error::Error,
+};
+
+/// The schema file and dotted path of a declaration.
+type Location = (String, String);
+
+/// The dotted path segments of `name` declared in `scope`.
+pub(crate) fn scoped(scope: &[String], name: &str) -> Vec<String> {
+    scope.iter().cloned().chain(Some(name.to_owned())).collect()
+}
+
+fn location(file: &str, scope: &[String], name: &str) -> Location {
+    (file.to_owned(), scoped(scope, name).join("."))
+}
+
+/// A name in the type namespace of a generated Rust module, which items and
+/// modules share, so only the name is compared.
+struct Generated {
+    kind: &'static str,
+    name: String,
+}
+
+impl PartialEq for Generated {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Generated {}
+
+impl Hash for Generated {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state)
+    }
+}
+
+fn item(name: &str) -> Generated {
+    Generated {
+        kind: "type",
+        name: rust_name(name),
+    }
+}
+
+fn module(name: String) -> Generated {
+    Generated {
+        kind: "module",
+        name,
+    }
+}
+
+/// Reports the names clashing in one generated Rust module.
+fn generated_names<I>(names: I) -> Vec<Error>
+where
+    I: IntoIterator<Item = (Generated, Location)>,
+{
+    duplicates(names, |first| {
+        format!("{} `{}` is already declared", first.kind, first.name)
+    })
+}
+
+/// The names a type, enum or component declared at `scope` generates in its
+/// module: its own, and the module of its nested declarations if any.
+fn declared_names(
+    file: &str,
+    scope: &[String],
+    name: &str,
+    types: &[Type],
+    enums: &[Enum],
+) -> Vec<(Generated, Location)> {
+    let declared = || location(file, scope, name);
+    let mut names = vec![(item(name), declared())];
+    if !types.is_empty() || !enums.is_empty() {
+        names.push((module(nested_module_name(name)), declared()));
+    }
+    names
+}
+
+/// The names generated in the module of the types and enums at `scope`.
+fn scope_names(
+    file: &str,
+    scope: &[String],
+    types: &[Type],
+    enums: &[Enum],
+) -> Vec<(Generated, Location)> {
+    types
+        .iter()
+        .flat_map(|ty| declared_names(file, scope, &ty.name, &ty.types, &ty.enums))
+        .chain(
+            enums
+                .iter()
+                .flat_map(|en| declared_names(file, scope, &en.name, &[], &[])),
+        )
+        .collect()
+}
+
+/// Reports every declaration whose key was already used by an earlier one.
+fn duplicates<K, I, M>(declarations: I, message: M) -> Vec<Error>
+where
+    K: Eq + Hash,
+    I: IntoIterator<Item = (K, Location)>,
+    M: Fn(&K) -> String,
+{
+    let mut first = HashMap::new();
+    declarations
+        .into_iter()
+        .filter_map(|(key, (file, declaration))| match first.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert((file, declaration));
+                None
+            }
+            Entry::Occupied(entry) => {
+                let (previous_file, previous_declaration) = entry.get().clone();
+                Some(Error::Duplicate {
+                    file,
+                    declaration,
+                    message: message(entry.key()),
+                    previous_file,
+                    previous_declaration,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Reports the declarations of one scope generating the same Rust name,
+/// given along with their schema name.
+fn rust_names<'a, I>(file: &str, scope: &[String], kind: &str, names: I) -> Vec<Error>
+where
+    I: IntoIterator<Item = (String, &'a str)>,
+{
+    duplicates(
+        names
+            .into_iter()
+            .map(|(rust, name)| (rust, location(file, scope, name))),
+        |rust| format!("{} `{}` is already declared", kind, rust),
+    )
+}
+
+fn field_ids(file: &str, scope: &[String], members: &[Member]) -> Vec<Error> {
+    duplicates(
+        members
+            .iter()
+            .map(|m| (m.id, location(file, scope, &m.name))),
+        |id| format!("field ID {} is already used", id),
+    )
+}
+
+fn field_names<'a, I>(file: &str, scope: &[String], names: I) -> Vec<Error>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    rust_names(
+        file,
+        scope,
+        "field",
+        names.into_iter().map(|name| (rust_name(name), name)),
+    )
+}
+
+fn validate_members(file: &str, scope: &[String], members: &[Member]) -> Vec<Error> {
+    field_ids(file, scope, members)
+        .into_iter()
+        .chain(field_names(
+            file,
+            scope,
+            members.iter().map(|m| m.name.as_str()),
+        ))
+        .collect()
+}
+
+/// Reports the fields, events and commands of the component at `scope` that
+/// share an ID, or a Rust name: they all become fields of its struct, and
+/// events and commands get type aliases named after them in camel case.
+pub(crate) fn validate_fields(file: &str, scope: &[String], comp: &Component) -> Vec<Error> {
+    let names = comp
+        .members
+        .iter()
+        .map(|m| m.name.as_str())
+        .chain(comp.events.iter().map(|e| e.name.as_str()))
+        .chain(comp.commands.iter().map(|c| c.name.as_str()));
+    let events = rust_names(
+        file,
+        scope,
+        "event",
+        comp.events
+            .iter()
+            .map(|e| (to_upper_camel_case(&e.name), e.name.as_str())),
+    );
+    let commands = rust_names(
+        file,
+        scope,
+        "command",
+        comp.commands
+            .iter()
+            .map(|c| (to_upper_camel_case(&c.name), c.name.as_str())),
+    );
+    field_ids(file, scope, &comp.members)
+        .into_iter()
+        .chain(field_names(file, scope, names))
+        .chain(events)
+        .chain(commands)
+        .collect()
+}
+
+fn validate_enum(file: &str, scope: &[String], en: &Enum) -> Vec<Error> {
+    let scope = scoped(scope, &en.name);
+    let values = duplicates(
+        en.variants
+            .iter()
+            .map(|v| (v.id, location(file, &scope, &v.name))),
+        |value| format!("enum value {} is already used", value),
+    );
+    let names = rust_names(
+        file,
+        &scope,
+        "enum value",
+        en.variants
+            .iter()
+            .map(|v| (rust_name(&to_upper_camel_case(&v.name)), v.name.as_str())),
+    );
+    values.into_iter().chain(names).collect()
+}
+
+fn validate_nested(file: &str, scope: &[String], types: &[Type], enums: &[Enum]) -> Vec<Error> {
+    types
+        .iter()
+        .flat_map(|ty| validate_type(file, scope, ty))
+        .chain(enums.iter().flat_map(|en| validate_enum(file, scope, en)))
+        .collect()
+}
+
+/// Validates the types and enums nested in the item at `scope`, which are
+/// generated in a module of their own.
+fn validate_scope(file: &str, scope: &[String], types: &[Type], enums: &[Enum]) -> Vec<Error> {
+    validate_nested(file, scope, types, enums)
+        .into_iter()
+        .chain(generated_names(scope_names(file, scope, types, enums)))
+        .collect()
+}
+
+fn validate_type(file: &str, scope: &[String], ty: &Type) -> Vec<Error> {
+    let scope = scoped(scope, &ty.name);
+    validate_members(file, &scope, &ty.members)
+        .into_iter()
+        .chain(validate_scope(file, &scope, &ty.types, &ty.enums))
+        .collect()
+}
+
+fn validate_component(file: &str, scope: &[String], comp: &Component) -> Vec<Error> {
+    let scope = scoped(scope, &comp.name);
+    validate_fields(file, &scope, comp)
+        .into_iter()
+        .chain(validate_scope(file, &scope, &comp.types, &comp.enums))
+        .collect()
+}
+
+fn validate_schema(schema: &SchemaFile) -> Vec<Error> {
+    let (file, scope) = (schema.path.as_str(), schema.package_name.as_slice());
+    let names = schema
+        .components
+        .iter()
+        .flat_map(|comp| declared_names(file, scope, &comp.name, &comp.types, &comp.enums))
+        .chain(scope_names(file, scope, &schema.types, &schema.enums));
+    schema
+        .components
+        .iter()
+        .flat_map(|comp| validate_component(file, scope, comp))
+        .chain(validate_nested(file, scope, &schema.types, &schema.enums))
+        .chain(generated_names(names))
+        .collect()
+}
+
+/// The names generated in the module of each package: the modules of its
+/// schema files and subpackages, and the items its files re-export. Items
+/// clashing within a file are left to `validate_schema`.
+fn package_names(files: &[&SchemaFile]) -> Vec<(Vec<String>, Generated, Location)> {
+    let mut subpackages = BTreeMap::new();
+    for schema in files {
+        for i in 1..schema.package_name.len() {
+            subpackages
+                .entry(schema.package_name[..=i].to_vec())
+                .or_insert_with(|| schema.path.clone());
+        }
+    }
+    let subpackages = subpackages.into_iter().map(|(package, file)| {
+        let (name, parent) = package.split_last().unwrap();
+        (
+            parent.to_vec(),
+            module(rust_name(name)),
+            (file, package.join(".")),
+        )
+    });
+    files
+        .iter()
+        .flat_map(|schema| {
+            let (file, package) = (schema.path.as_str(), &schema.package_name);
+            let mut exported = HashSet::new();
+            let items = schema
+                .components
+                .iter()
+                .map(|comp| &comp.name)
+                .chain(schema.types.iter().map(|ty| &ty.name))
+                .chain(schema.enums.iter().map(|en| &en.name))
+                .filter(move |name| exported.insert(rust_name(name)))
+                .map(move |name| (package.clone(), item(name), location(file, package, name)));
+            Some((
+                package.clone(),
+                module(rust_name(&schema.name)),
+                location(file, package, &schema.name),
+            ))
+            .into_iter()
+            .chain(items)
+        })
+        .chain(subpackages)
+        .collect()
+}
+
+/// Checks the AST for declarations SpatialOS would only reject at runtime:
+/// fields sharing an ID or a name, components sharing an ID and enum values
+/// sharing a value or a name. Names are compared as generated in Rust, along
+/// with the modules generated next to them: the module of a package holds
+/// those of its files and subpackages and re-exports the items of its files,
+/// and an item with nested declarations gets a module of its own. Every
+/// conflict is reported along with the declaration it conflicts with.
+pub fn validate(ast: &AST) -> Result<(), Error> {
+    let mut files = Vec::new();
+    ast.inner.iter().for_each(|node| node.schemas(&mut files));
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let component_ids = duplicates(
+        files.iter().flat_map(|schema| {
+            schema.components.iter().map(move |comp| {
+                (
+                    comp.id,
+                    location(&schema.path, &schema.package_name, &comp.name),
+                )
+            })
+        }),
+        |id| format!("component ID {} is already used", id),
+    );
+    let package_names = duplicates(
+        package_names(&files)
+            .into_iter()
+            .map(|(package, name, location)| ((package, name), location)),
+        |(package, first)| {
+            format!(
+                "{} `{}` is already declared in package `{}`",
+                first.kind,
+                first.name,
+                package.join(".")
+            )
+        },
+    );
+    let errors = files
+        .iter()
+        .flat_map(|schema| validate_schema(schema))
+        .chain(component_ids)
+        .chain(package_names)
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::multiple(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{ast::ASTNode, parser::schema_file::parse_schema};
+
+    fn ast(files: &[(&str, &str)]) -> AST {
+        AST {
+            inner: files
+                .iter()
+                .map(|(path, source)| {
+                    let schema = parse_schema(path, source.as_bytes())
+                        .unwrap()
+                        .with_name(
+                            path.trim_end_matches(".schema")
+                                .rsplit('/')
+                                .next()
+                                .unwrap()
+                                .to_owned(),
+                        )
+                        .with_path(path.to_string())
+                        .build()
+                        .unwrap();
+                    ASTNode::package_schema(&schema, &schema.package_name)
+                })
+                .collect(),
+        }
+    }
+
+    fn messages(ast: &AST) -> Vec<String> {
+        match validate(ast) {
+            Ok(()) => vec![],
+            Err(Error::Multiple(errors)) => errors.iter().map(|e| e.to_string()).collect(),
+            Err(e) => vec![e.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_validate_valid_schema() {
+        let ast = ast(&[(
+            "game.schema",
+            "package game;
+            enum State { ALIVE = 0; DEAD = 1; }
+            type Stats { uint32 hp = 1; uint32 mana = 2; }
+            component Health { id = 1; uint32 hp = 1; Stats stats = 2; }",
+        )]);
+        assert_eq!(messages(&ast), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_validate_duplicates() {
+        let ast = ast(&[
+            (
+                "game.schema",
+                "package game;
+                enum State { ALIVE = 0; DEAD = 0; ALIVE = 2; }
+                type Stats {
+                    type Inner { uint32 a = 1; uint32 b = 1; }
+                    uint32 hp = 1;
+                    uint32 hp = 2;
+                }
+                component Health { id = 1; uint32 hp = 1; uint32 max_hp = 1; }",
+            ),
+            (
+                "other.schema",
+                "package other;
+                component Mana { id = 1; }",
+            ),
+        ]);
+        assert_eq!(
+            messages(&ast),
+            vec![
+                "game.schema: game.Health.max_hp: field ID 1 is already used \
+                 (first declared as game.Health.hp in game.schema)",
+                "game.schema: game.Stats.hp: field `hp` is already declared \
+                 (first declared as game.Stats.hp in game.schema)",
+                "game.schema: game.Stats.Inner.b: field ID 1 is already used \
+                 (first declared as game.Stats.Inner.a in game.schema)",
+                "game.schema: game.State.DEAD: enum value 0 is already used \
+                 (first declared as game.State.ALIVE in game.schema)",
+                "game.schema: game.State.ALIVE: enum value `Alive` is already declared \
+                 (first declared as game.State.ALIVE in game.schema)",
+                "other.schema: other.Mana: component ID 1 is already used \
+                 (first declared as game.Health in game.schema)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_rust_name_clashes() {
+        let ast = ast(&[
+            (
+                "g/a.schema",
+                "package g;
+                type Q {}
+                enum Level { FOO_BAR = 0; FOO__BAR = 1; }",
+            ),
+            ("g/b.schema", "package g; enum Q { A = 0; }"),
+            ("h/q.schema", "package h; type Q {}"),
+            (
+                "g/c.schema",
+                "package g;
+                type Outer { type Inner {} enum Inner { A = 0; } }
+                component Target {
+                    id = 1;
+                    event uint32 hit;
+                    event uint32 HIT;
+                    command uint32 hit(uint32);
+                }",
+            ),
+        ]);
+        assert_eq!(
+            messages(&ast),
+            vec![
+                "g/a.schema: g.Level.FOO__BAR: enum value `FooBar` is already declared \
+                 (first declared as g.Level.FOO_BAR in g/a.schema)",
+                "g/c.schema: g.Target.hit: field `hit` is already declared \
+                 (first declared as g.Target.hit in g/c.schema)",
+                "g/c.schema: g.Target.HIT: event `Hit` is already declared \
+                 (first declared as g.Target.hit in g/c.schema)",
+                "g/c.schema: g.Outer.Inner: type `Inner` is already declared \
+                 (first declared as g.Outer.Inner in g/c.schema)",
+                "g/b.schema: g.Q: type `Q` is already declared in package `g` \
+                 (first declared as g.Q in g/a.schema)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_module_clashes() {
+        let ast = ast(&[
+            (
+                "g/a.schema",
+                "package g;
+                type HTTPRequest { type A {} }
+                type HttpRequest { type B {} }
+                type Query { enum C { D = 0; } }
+                type query {}",
+            ),
+            ("g/match.schema", "package g; type match {}"),
+            (
+                "g/h.schema",
+                "package g; type h { type Inner { type X {} } type inner {} }",
+            ),
+            ("g/h/i.schema", "package g.h; type I {}"),
+        ]);
+        assert_eq!(
+            messages(&ast),
+            vec![
+                "g/a.schema: g.HttpRequest: module `http_request` is already declared \
+                 (first declared as g.HTTPRequest in g/a.schema)",
+                "g/a.schema: g.query: module `query` is already declared \
+                 (first declared as g.Query in g/a.schema)",
+                "g/h.schema: g.h.inner: module `inner` is already declared \
+                 (first declared as g.h.Inner in g/h.schema)",
+                "g/h.schema: g.h: module `h` is already declared in package `g` \
+                 (first declared as g.h in g/h.schema)",
+                "g/match.schema: g.match: module `r#match` is already declared in package `g` \
+                 (first declared as g.match in g/match.schema)",
+                "g/h/i.schema: g.h: module `h` is already declared in package `g` \
+                 (first declared as g.h in g/h.schema)",
+            ]
+        );
+    }
+}